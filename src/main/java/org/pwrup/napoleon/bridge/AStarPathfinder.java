@@ -46,18 +46,391 @@ public class AStarPathfinder implements AutoCloseable {
 
   public native int[] calculate(int[] start_x_y, int[] end_x_y);
 
+  public native int[] calculateVia(
+    int[] start_x_y,
+    int[] via_x_y,
+    int[] end_x_y,
+    boolean reorder_via_points
+  );
+
+  /**
+   * Solves many queries in parallel against the current grid.
+   * @param start_end_x_y {startX, startY, endX, endY, ...} for every query
+   * @return one path per query in the same order, empty if there is no path
+   */
+  public native int[][] calculateBatch(int[] start_end_x_y);
+
+  public native int[][] calculateAlternatives(
+    int[] start_x_y,
+    int[] end_x_y,
+    int pathCount,
+    float maxOverlap,
+    int corridorRadius,
+    float penalty,
+    int maxAttempts
+  );
+
+  /**
+   * Re-evaluates a path against the current grid state.
+   * @return {firstInvalidIndex, cost, unknownShare}, where firstInvalidIndex is -1 if the path is
+   * still valid and unknownShare is the share of the path's steps through unknown cells (0 to 1)
+   */
+  public native float[] validatePath(int[] path_x_y);
+
+  /**
+   * Adds (obstructed = true) or removes static obstacles.
+   * @param obstacles {x, y, x, y, ...} cell positions
+   */
+  public native void setStaticObstacles(int[] obstacles, boolean obstructed);
+
+  /**
+   * Marks cells as unknown (unknown = true) or as known free. Unknown cells lose their static
+   * obstacle.
+   * @param cells {x, y, x, y, ...} cell positions
+   */
+  public native void setUnknownCells(int[] cells, boolean unknown);
+
+  /**
+   * Sets how searches treat unknown cells.
+   * @param policy 0 = free, 1 = occupied, 2 = free with the given cost per cell
+   */
+  public native void setUnknownPolicy(int policy, float cost);
+
+  /**
+   * Sets how searches treat cells near hybrid objects of one class. Once any class is configured,
+   * hybrid objects leave the average distance cost, and objects of classes never configured only
+   * block the cells inside them.
+   * @param objectClass 0 robot, 1 game piece, 2 human, 3 partner robot, 4 opponent robot,
+   *                    anything else unknown
+   * @param clearance how far from the object's edge cells are affected, in grid cells
+   * @param cost the extra cost at the object's edge, fading to 0 at the clearance
+   * @param hard whether affected cells are refused instead of charged
+   */
+  public native void setObjectAvoidance(
+    int objectClass,
+    float clearance,
+    float cost,
+    boolean hard
+  );
+
+  /**
+   * Sets the traversal cost of cells that can be crossed but should be avoided. Crossing a cell
+   * with cost c costs (1 + c) times the distance.
+   * @param cells {x, y, x, y, ...} cell positions
+   * @param cost the new cost, 0 makes the cells free to cross again
+   */
+  public native void setCellCosts(int[] cells, float cost);
+
+  public native void clearCellCosts();
+
+  /** Removes every static obstacle between the two corners (inclusive). */
+  public native void clearStaticRegion(int[] min_x_y, int[] max_x_y);
+
+  /*
+   * Geometric obstacles in field meters. Conservative coverage blocks every cell the shape
+   * touches, otherwise only cells whose center is inside the shape are blocked.
+   */
+
+  /** @param corners_x_y {x, y, x, y, ...} corners in order, convex or concave */
+  public native void addObstaclePolygon(float[] corners_x_y, boolean conservative);
+
+  public native void addObstacleCircle(
+    float[] center,
+    float radius,
+    boolean conservative
+  );
+
+  /** @param rotation counterclockwise rotation about the center in radians */
+  public native void addObstacleRectangle(
+    float[] center,
+    float[] size,
+    float rotation,
+    boolean conservative
+  );
+
+  /** @param thickness the full width of the segment in meters */
+  public native void addObstacleSegment(
+    float[] start,
+    float[] end,
+    float thickness,
+    boolean conservative
+  );
+
+  /*
+   * Keep-out zones are only refused while one of their time windows is active. Windows are
+   * {start, end, start, end, ...} in match seconds (see setMatchTime), end exclusive. Each call
+   * returns the id of the new zone.
+   */
+
+  /** @param corners_x_y {x, y, x, y, ...} corners in field meters */
+  public native long addKeepOutPolygon(
+    float[] corners_x_y,
+    float[] windows,
+    boolean conservative
+  );
+
+  /** @param cells {x, y, x, y, ...} cell positions */
+  public native long addKeepOutCells(int[] cells, float[] windows);
+
+  /** @return whether there was a zone with that id */
+  public native boolean removeKeepOutZone(long id);
+
+  public native void clearKeepOutZones();
+
+  /**
+   * Sets how fast the robot crosses cells, so keep-out zones are checked at the time each cell
+   * would actually be reached rather than now.
+   * @param cellsPerSecond 0 or less checks every cell at the current match time
+   */
+  public native void setTravelSpeed(float cellsPerSecond);
+
+  /**
+   * Adds a named area of the field, replacing any zone with the same name.
+   * @param corners_x_y {x, y, x, y, ...} corners in field meters
+   * @param approach_x_y_heading {x, y, heading, ...} poses to stand at to use the zone, in field
+   * meters and radians counterclockwise from +x, may be empty
+   */
+  public native void addZone(
+    String name,
+    float[] corners_x_y,
+    float[] approach_x_y_heading
+  );
+
+  /** @return whether there was a zone with that name */
+  public native boolean removeZone(String name);
+
+  /**
+   * Replaces the named zones with the "zones" of a JSON map file.
+   * @return how many zones were loaded, or -1 if the file can't be read
+   */
+  public native int loadZones(String fileName);
+
+  /**
+   * Replaces the grid with a built-in field ("crescendo_2024" or "reefscape_2025") built at the
+   * current square size, with its walls, structures and "blue_..."/"red_..." zones. The grid then
+   * covers the whole field, whatever size it was initialized with.
+   * @return whether there is a preset with that name
+   */
+  public boolean loadPreset(String name) {
+    if (!loadPresetGrid(name)) {
+      return false;
+    }
+
+    // The preset brings its own size, so the grid this was created with is out of date
+    int[] layout = getGridLayout();
+    this.hybridGrid =
+      new HybridGrid(
+        layout[0],
+        layout[1],
+        layout[2],
+        layout[3],
+        hybridGrid.getSqSizeMeters(),
+        getStaticObstacles()
+      );
+    return true;
+  }
+
+  private native boolean loadPresetGrid(String name);
+
+  /** @return {widthX, widthY, centerX, centerY} of the current grid in cells */
+  private native int[] getGridLayout();
+
+  /** @return {x, y, x, y, ...} the current static obstacles */
+  private native int[] getStaticObstacles();
+
+  /**
+   * @param position_x_y a position in field meters
+   * @return the names of every zone containing the position, sorted
+   */
+  public native String[] getZonesAt(float[] position_x_y);
+
+  /**
+   * @param position_x_y a position in field meters
+   * @return the name of the closest zone, or null if there are none
+   */
+  public native String getNearestZone(float[] position_x_y);
+
+  /**
+   * Plans to the cheapest approach pose of a zone, or into the zone if it has none.
+   * @return the path in the format of calculate, empty if the zone is unknown or unreachable
+   */
+  public native int[] calculateToZone(int[] start_x_y, String name);
+
+  /**
+   * Replaces the grid with the same field seen from the other alliance, flipping obstacles, costs,
+   * observations, keep-out zones, named zones, objects, uncertainty fields and heat. IDs and names
+   * are kept. Forward (+y) runs between the alliance walls.
+   * @param symmetry 0 mirrors across the center line (y only), 1 turns 180° about the center
+   */
+  public native void flipAlliance(int symmetry);
+
+  /**
+   * @param path_x_y {x, y, x, y, ...} cell positions
+   * @param symmetry as in flipAlliance
+   * @return the path in the other alliance's frame, in the same format
+   */
+  public native int[] flipPath(int[] path_x_y, int symmetry);
+
+  /**
+   * Integrates sensor readings into the observed occupancy. Every ray clears the cells it passes
+   * and, if isHit, counts as a detection at its end. Cells only become obstacles after repeated
+   * detections and are cleared again by later rays through them.
+   * @param sensor_x_y where the sensor was, in field meters
+   * @param ends_x_y {x, y, x, y, ...} where each ray ended, in field meters
+   * @param isHit false for readings at the sensor's maximum range
+   */
+  public native void addObservations(
+    float[] sensor_x_y,
+    float[] ends_x_y,
+    boolean isHit
+  );
+
+  public native void clearObservations();
+
   public native void clearHybridObjects();
 
   public native void addHybridObjects(float[] objects);
 
+  /**
+   * Adds a hybrid object or, if one with the same id exists, moves and updates it. Detections can
+   * be upserted every frame instead of clearing and re-adding everything.
+   * @param id a stable id for the object, e.g. its tracker id
+   * @param position_x_y where the object is, in grid cells
+   * @param radius the object's radius, in grid cells
+   * @param objectClass 0 robot, 1 game piece, 2 human, 3 partner robot, 4 opponent robot,
+   *                    anything else unknown
+   * @param timestamp when the object was seen, in match time seconds
+   * @param timeToLive how long after timestamp the object is forgotten, 0 or less to keep it
+   */
+  public native void upsertHybridObject(
+    long id,
+    float[] position_x_y,
+    float radius,
+    int objectClass,
+    double timestamp,
+    double timeToLive
+  );
+
+  /**
+   * @return whether there was a hybrid object with that id
+   */
+  public native boolean removeHybridObject(long id);
+
+  /**
+   * Advances the match clock, in seconds. Hybrid objects and uncertainty fields past their time
+   * to live are forgotten.
+   */
+  public native void setMatchTime(double time);
+
+  /**
+   * Adds heat where each opponent robot among the hybrid objects is now. Searches avoid hot cells,
+   * so calling this on every tracker update teaches them the lanes opponents keep using. Heat
+   * fades over match time (see setMatchTime).
+   * @param amount the heat added at each opponent's center, as an extra cost per cell
+   * @return how many opponents were recorded
+   */
+  public native int recordOpponentHeat(float amount);
+
+  /**
+   * Adds heat for an opponent seen by something other than the hybrid objects.
+   * @param position_x_y the opponent's position, in grid cells
+   * @param radius how far the heat spreads, in grid cells
+   */
+  public native void addOpponentHeat(
+    float[] position_x_y,
+    float radius,
+    float amount
+  );
+
+  /**
+   * Forgets all opponent heat.
+   * @param decayRate the fraction of heat lost per second from now on, exponentially
+   */
+  public native void resetOpponentHeatmap(float decayRate);
+
   public native void clearUncertentyFields();
 
-  public native void addUncertentyField(
+  /**
+   * A circular field whose cost is lowest at the center and eases up to the full intensity at
+   * the radius (falloff 4 below).
+   * @return the id of the new field
+   */
+  public native long addUncertentyField(
     float[] center,
     float radius,
     float intensity
   );
 
+  /**
+   * Moves, resizes and refreshes a field, which also restarts its decay.
+   * @param center the new center, in grid cells
+   * @param scale multiplies the field's current size, 1 keeps it
+   * @param intensity the new full intensity, which the field's falloff scales, so with the
+   * default ease out curve it is reached at the edge and the center costs least
+   * @return whether there was a field with that id
+   */
+  public native boolean updateUncertentyField(
+    long id,
+    float[] center,
+    float scale,
+    float intensity
+  );
+
+  /**
+   * Makes a field fade while its source isn't seen. Decay and expiry count from the last add or
+   * update, in match time (see setMatchTime).
+   * @param decayRate the fraction of intensity lost per second, exponentially
+   * @param timeToLive how long until the field is removed, 0 or less to keep it
+   * @return whether there was a field with that id
+   */
+  public native boolean setUncertentyFieldDecay(
+    long id,
+    float decayRate,
+    double timeToLive
+  );
+
+  /** @return whether there was a field with that id */
+  public native boolean removeUncertentyField(long id);
+
+  /*
+   * Shaped uncertainty fields in grid cells. The falloff sets how the cost changes from the
+   * center to the edge. 0 linear, 1 quadratic and 2 gaussian fade from the full intensity at the
+   * center, 3 (step) is the full intensity everywhere. 4, and anything else, is the ease out curve
+   * plain circular fields use, which is lowest at the center and rises to the full intensity at
+   * the edge. Each returns the id of the new field.
+   */
+
+  /** @param rotation counterclockwise rotation of the radii about the center in radians */
+  public native long addUncertentyEllipse(
+    float[] center,
+    float[] radii,
+    float rotation,
+    float intensity,
+    int falloff
+  );
+
+  /** @param rotation counterclockwise rotation about the center in radians */
+  public native long addUncertentyRectangle(
+    float[] center,
+    float[] size,
+    float rotation,
+    float intensity,
+    int falloff
+  );
+
+  /**
+   * A position estimate reaching out to three standard deviations. With the gaussian falloff the
+   * cost follows the estimate's density.
+   * @param covariance {xx, xy, yy} in grid cells squared
+   * @return the id of the new field, or -1 if covariance has fewer than three values
+   */
+  public native long addUncertentyGaussian(
+    float[] center,
+    float[] covariance,
+    float intensity,
+    int falloff
+  );
+
   private native void cleanup();
 
   @Override
@@ -39,6 +39,23 @@ public class HybridGrid {
     this.staticObstacles = list;
   }
 
+  /** @param staticObstacles {x, y, x, y, ...} cell positions */
+  public HybridGrid(
+    int widthX,
+    int widthY,
+    int centerX,
+    int centerY,
+    float sqSizeMeters,
+    int[] staticObstacles
+  ) {
+    this.widthX = widthX;
+    this.widthY = widthY;
+    this.centerX = centerX;
+    this.centerY = centerY;
+    this.sqSizeMeters = sqSizeMeters;
+    this.staticObstacles = staticObstacles;
+  }
+
   public HybridGrid(String fileName) {
     try {
       JSONParser parser = new JSONParser();
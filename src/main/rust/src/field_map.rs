@@ -0,0 +1,668 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    path::Path,
+};
+
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::hybrid_grid::{
+    shapes::{Coverage, ObstacleShape},
+    zones::{ApproachPose, SemanticZone},
+    HybridGrid,
+};
+
+pub mod occupancy;
+pub mod presets;
+
+#[derive(Debug)]
+pub enum MapError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Yaml(serde_yaml::Error),
+    Image(image::ImageError),
+    /// The file parsed but describes an impossible map
+    Invalid(String),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::Io(error) => write!(f, "failed to access map file: {}", error),
+            MapError::Json(error) => write!(f, "failed to parse map: {}", error),
+            MapError::Yaml(error) => write!(f, "failed to parse map metadata: {}", error),
+            MapError::Image(error) => write!(f, "failed to read map image: {}", error),
+            MapError::Invalid(reason) => write!(f, "invalid map: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for MapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MapError::Io(error) => Some(error),
+            MapError::Json(error) => Some(error),
+            MapError::Yaml(error) => Some(error),
+            MapError::Image(error) => Some(error),
+            MapError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for MapError {
+    fn from(error: io::Error) -> Self {
+        MapError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for MapError {
+    fn from(error: serde_json::Error) -> Self {
+        MapError::Json(error)
+    }
+}
+
+impl From<serde_yaml::Error> for MapError {
+    fn from(error: serde_yaml::Error) -> Self {
+        MapError::Yaml(error)
+    }
+}
+
+impl From<image::ImageError> for MapError {
+    fn from(error: image::ImageError) -> Self {
+        MapError::Image(error)
+    }
+}
+
+/// A cell position or a size in cells.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MapCell {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A position in field meters.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MapPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl From<MapPoint> for Vector2<f32> {
+    fn from(point: MapPoint) -> Self {
+        Vector2::new(point.x, point.y)
+    }
+}
+
+impl From<Vector2<f32>> for MapPoint {
+    fn from(point: Vector2<f32>) -> Self {
+        MapPoint {
+            x: point.x,
+            y: point.y,
+        }
+    }
+}
+
+/// A position in field meters with a heading in radians counterclockwise from +x.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MapPose {
+    pub x: f32,
+    pub y: f32,
+    #[serde(default)]
+    pub heading: f32,
+}
+
+/// A cell with a traversal cost.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MapCellCost {
+    pub x: i32,
+    pub y: i32,
+    pub cost: f32,
+}
+
+/// An area that can be crossed but should be avoided, e.g. a ramp or a cable protector.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MapCostRegion {
+    /// The outline in field meters, covering every cell whose center is inside
+    pub polygon: Vec<MapPoint>,
+    pub cost: f32,
+}
+
+/// A named area of the field, e.g. a scoring position or a loading station.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MapZone {
+    pub name: String,
+    /// The outline in field meters
+    pub polygon: Vec<MapPoint>,
+    /// Where to stand to use the zone, see `SemanticZone::approach_poses`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub approach_poses: Vec<MapPose>,
+}
+
+impl From<&MapZone> for SemanticZone {
+    fn from(zone: &MapZone) -> Self {
+        SemanticZone::new(
+            zone.name.clone(),
+            zone.polygon.iter().map(|&point| point.into()).collect(),
+        )
+        .with_approach_poses(
+            zone.approach_poses
+                .iter()
+                .map(|pose| ApproachPose::new(Vector2::new(pose.x, pose.y), pose.heading))
+                .collect(),
+        )
+    }
+}
+
+impl From<&SemanticZone> for MapZone {
+    fn from(zone: &SemanticZone) -> Self {
+        MapZone {
+            name: zone.name.clone(),
+            polygon: zone.polygon.iter().map(|&point| point.into()).collect(),
+            approach_poses: zone
+                .approach_poses
+                .iter()
+                .map(|pose| MapPose {
+                    x: pose.position.x,
+                    y: pose.position.y,
+                    heading: pose.heading,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The JSON map format shared with the Java `HybridGrid(String fileName)` constructor. Only
+/// `square_size_meters`, a size and `obstacles` are understood by Java, everything else is an
+/// optional extension that older files simply leave out.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldMap {
+    pub square_size_meters: f32,
+    /// The size of a square grid in cells
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grid_size_pixels: Option<i32>,
+    /// The size of a rectangular grid in cells, takes precedence over `grid_size_pixels`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grid_size: Option<MapCell>,
+    /// The center cell of the grid, half the size when left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub center: Option<MapCell>,
+    /// Obstructed cells
+    #[serde(default)]
+    pub obstacles: Vec<MapCell>,
+    /// Cells nothing is known about yet
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown: Vec<MapCell>,
+    /// Obstacle outlines in field meters, blocking every cell they touch
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub polygons: Vec<Vec<MapPoint>>,
+    /// Traversal costs of single cells, see `HybridGrid::set_cell_costs`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cell_costs: Vec<MapCellCost>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cost_regions: Vec<MapCostRegion>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub zones: Vec<MapZone>,
+}
+
+impl FieldMap {
+    /// # Returns
+    /// The map described by `json`, or why it can't be used.
+    pub fn from_json(json: &str) -> Result<Self, MapError> {
+        let map: FieldMap = serde_json::from_str(json)?;
+        map.validate()?;
+
+        Ok(map)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MapError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, MapError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), MapError> {
+        fs::write(path, self.to_json()?)?;
+
+        Ok(())
+    }
+
+    /// # Returns
+    /// A map of the static obstacles, costs, unknown cells and named zones of `grid`. Square grids
+    /// are written with `grid_size_pixels` so the Java loader can read the file too.
+    pub fn from_grid(grid: &HybridGrid) -> Self {
+        let size = grid.get_size();
+        let center = grid.get_center();
+
+        let mut obstacles: Vec<MapCell> = grid
+            .get_static_obstacles()
+            .iter()
+            .map(|obstacle| MapCell {
+                x: obstacle.x,
+                y: obstacle.y,
+            })
+            .collect();
+        obstacles.sort_by_key(|obstacle| (obstacle.x, obstacle.y));
+
+        let mut unknown: Vec<MapCell> = grid
+            .get_unknown_cells()
+            .iter()
+            .map(|cell| MapCell {
+                x: cell.x,
+                y: cell.y,
+            })
+            .collect();
+        unknown.sort_by_key(|cell| (cell.x, cell.y));
+
+        let mut cell_costs: Vec<MapCellCost> = grid
+            .get_cell_costs()
+            .iter()
+            .map(|(cell, cost)| MapCellCost {
+                x: cell.x,
+                y: cell.y,
+                cost: *cost,
+            })
+            .collect();
+        cell_costs.sort_by_key(|cell| (cell.x, cell.y));
+
+        let mut zones: Vec<MapZone> = grid
+            .get_semantic_zones()
+            .values()
+            .map(MapZone::from)
+            .collect();
+        zones.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let is_square = size.x == size.y;
+        Self {
+            square_size_meters: grid.get_square_size_meters(),
+            grid_size_pixels: is_square.then_some(size.x),
+            grid_size: (!is_square).then_some(MapCell {
+                x: size.x,
+                y: size.y,
+            }),
+            center: (center != size / 2).then_some(MapCell {
+                x: center.x,
+                y: center.y,
+            }),
+            obstacles,
+            unknown,
+            polygons: Vec::new(),
+            cell_costs,
+            cost_regions: Vec::new(),
+            zones,
+        }
+    }
+
+    /// # Returns
+    /// The number of cells on both axes, zero if the map gives no size.
+    pub fn get_size(&self) -> Vector2<i32> {
+        match (self.grid_size, self.grid_size_pixels) {
+            (Some(size), _) => Vector2::new(size.x, size.y),
+            (None, Some(size)) => Vector2::new(size, size),
+            (None, None) => Vector2::zeros(),
+        }
+    }
+
+    pub fn get_center(&self) -> Vector2<i32> {
+        self.center
+            .map(|center| Vector2::new(center.x, center.y))
+            .unwrap_or_else(|| self.get_size() / 2)
+    }
+
+    /// # Returns
+    /// The same field with cells of `square_size_meters`, covering at least the same area. Only
+    /// geometry given in meters can be resampled, so maps with obstacle, unknown or cost cells are
+    /// rejected.
+    pub fn with_resolution(&self, square_size_meters: f32) -> Result<Self, MapError> {
+        if !self.obstacles.is_empty() || !self.unknown.is_empty() || !self.cell_costs.is_empty() {
+            return Err(invalid(
+                "only maps without obstacle, unknown or cost cells can change resolution",
+            ));
+        }
+        if !square_size_meters.is_finite() || square_size_meters <= 0.0 {
+            return Err(invalid(format!(
+                "square_size_meters must be positive, got {}",
+                square_size_meters
+            )));
+        }
+
+        let size = self.get_size();
+        let min_meters = (self.get_center() - size / 2).cast::<f32>() * self.square_size_meters;
+        let max_meters = min_meters + size.cast::<f32>() * self.square_size_meters;
+
+        // The tolerance keeps rounding errors from adding a whole row of cells
+        let min = (min_meters / square_size_meters).map(|cells| (cells + 1e-3).floor() as i32);
+        let max = (max_meters / square_size_meters).map(|cells| (cells - 1e-3).ceil() as i32);
+        // Odd sizes would lose their last cell to the integer halving around the center
+        let new_size = (max - min).map(|cells| cells + cells % 2);
+        let center = min + new_size / 2;
+
+        let is_square = new_size.x == new_size.y;
+        let map = Self {
+            square_size_meters,
+            grid_size_pixels: is_square.then_some(new_size.x),
+            grid_size: (!is_square).then_some(MapCell {
+                x: new_size.x,
+                y: new_size.y,
+            }),
+            center: (center != new_size / 2).then_some(MapCell {
+                x: center.x,
+                y: center.y,
+            }),
+            ..self.clone()
+        };
+        map.validate()?;
+
+        Ok(map)
+    }
+
+    pub fn get_zone(&self, name: &str) -> Option<&MapZone> {
+        self.zones.iter().find(|zone| zone.name == name)
+    }
+
+    /// # Returns
+    /// The grid described by the map, with every polygon rasterized into static obstacles, every
+    /// cost region into cell costs and every zone added as a named zone.
+    pub fn build_grid(&self) -> HybridGrid {
+        let size = self.get_size();
+        let center = self.get_center();
+        let obstacles = self
+            .obstacles
+            .iter()
+            .map(|obstacle| Vector2::new(obstacle.x, obstacle.y))
+            .collect();
+
+        let mut grid = HybridGrid::new(
+            size.x,
+            size.y,
+            self.square_size_meters,
+            obstacles,
+            center.x,
+            center.y,
+        );
+        let unknown: Vec<Vector2<i32>> = self
+            .unknown
+            .iter()
+            .map(|cell| Vector2::new(cell.x, cell.y))
+            .collect();
+        grid.set_unknown_cells(&unknown, true);
+
+        for polygon in &self.polygons {
+            let shape = ObstacleShape::Polygon(polygon.iter().map(|&point| point.into()).collect());
+            grid.add_obstacle_shape(&shape, Coverage::Conservative);
+        }
+
+        // Cells sharing a cost are set together so a large map doesn't flood the dirty regions
+        let mut costs: HashMap<u32, Vec<Vector2<i32>>> = HashMap::new();
+        for cell in &self.cell_costs {
+            costs
+                .entry(cell.cost.to_bits())
+                .or_default()
+                .push(Vector2::new(cell.x, cell.y));
+        }
+        for (cost, cells) in costs {
+            grid.set_cell_costs(&cells, f32::from_bits(cost));
+        }
+
+        for region in &self.cost_regions {
+            let shape =
+                ObstacleShape::Polygon(region.polygon.iter().map(|&point| point.into()).collect());
+            grid.add_cost_shape(&shape, Coverage::Exact, region.cost);
+        }
+
+        for zone in &self.zones {
+            grid.set_semantic_zone(zone.into());
+        }
+
+        grid
+    }
+
+    pub fn validate(&self) -> Result<(), MapError> {
+        if !self.square_size_meters.is_finite() || self.square_size_meters <= 0.0 {
+            return Err(invalid(format!(
+                "square_size_meters must be positive, got {}",
+                self.square_size_meters
+            )));
+        }
+
+        if self.grid_size.is_none() && self.grid_size_pixels.is_none() {
+            return Err(invalid("either grid_size_pixels or grid_size is required"));
+        }
+
+        let size = self.get_size();
+        if size.x <= 0 || size.y <= 0 {
+            return Err(invalid(format!(
+                "grid size must be positive, got {}x{}",
+                size.x, size.y
+            )));
+        }
+
+        let grid = HybridGrid::new_raw(
+            size.x,
+            size.y,
+            self.square_size_meters,
+            self.get_center().x,
+            self.get_center().y,
+        );
+        if let Some(cell) = self
+            .obstacles
+            .iter()
+            .chain(&self.unknown)
+            .map(|cell| Vector2::new(cell.x, cell.y))
+            .chain(
+                self.cell_costs
+                    .iter()
+                    .map(|cell| Vector2::new(cell.x, cell.y)),
+            )
+            .find(|cell| grid.is_outside_grid(*cell))
+        {
+            return Err(invalid(format!(
+                "cell ({}, {}) is outside the grid",
+                cell.x, cell.y
+            )));
+        }
+
+        for (i, polygon) in self.polygons.iter().enumerate() {
+            validate_outline(polygon, &format!("polygon {}", i))?;
+        }
+
+        let costs = self
+            .cell_costs
+            .iter()
+            .map(|cell| cell.cost)
+            .chain(self.cost_regions.iter().map(|region| region.cost));
+        for cost in costs {
+            if !cost.is_finite() || cost < 0.0 {
+                return Err(invalid(format!("costs must be 0 or more, got {}", cost)));
+            }
+        }
+
+        for (i, region) in self.cost_regions.iter().enumerate() {
+            validate_outline(&region.polygon, &format!("cost region {}", i))?;
+        }
+
+        let mut names = HashSet::new();
+        for zone in &self.zones {
+            if zone.name.is_empty() {
+                return Err(invalid("zone names can't be empty"));
+            }
+            if !names.insert(zone.name.as_str()) {
+                return Err(invalid(format!("zone \"{}\" is defined twice", zone.name)));
+            }
+            validate_outline(&zone.polygon, &format!("zone \"{}\"", zone.name))?;
+            if zone
+                .approach_poses
+                .iter()
+                .any(|pose| !pose.x.is_finite() || !pose.y.is_finite() || !pose.heading.is_finite())
+            {
+                return Err(invalid(format!(
+                    "zone \"{}\" has an approach pose that is not a number",
+                    zone.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn invalid(reason: impl Into<String>) -> MapError {
+    MapError::Invalid(reason.into())
+}
+
+fn validate_outline(outline: &[MapPoint], name: &str) -> Result<(), MapError> {
+    if outline.len() < 3 {
+        return Err(invalid(format!(
+            "{} needs at least 3 corners, got {}",
+            name,
+            outline.len()
+        )));
+    }
+
+    if outline
+        .iter()
+        .any(|point| !point.x.is_finite() || !point.y.is_finite())
+    {
+        return Err(invalid(format!(
+            "{} has a corner that is not a number",
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_java_format() {
+        let map = FieldMap::from_json(
+            r#"{
+                "square_size_meters": 0.5,
+                "grid_size_pixels": 10,
+                "obstacles": [{"x": 1, "y": 2}, {"x": 3, "y": 4}]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(map.get_size(), Vector2::new(10, 10));
+        assert_eq!(map.get_center(), Vector2::new(5, 5));
+
+        let grid = map.build_grid();
+        assert!(grid.is_obstructed(Vector2::new(1, 2)));
+        assert!(grid.is_obstructed(Vector2::new(3, 4)));
+        assert_eq!(grid.get_static_obstacles().len(), 2);
+    }
+
+    #[test]
+    fn test_extended_format() {
+        let map = FieldMap::from_json(
+            r#"{
+                "square_size_meters": 0.5,
+                "grid_size": {"x": 20, "y": 10},
+                "center": {"x": 10, "y": 5},
+                "polygons": [[{"x": 1.0, "y": 1.0}, {"x": 2.0, "y": 1.0}, {"x": 2.0, "y": 2.0}]],
+                "cell_costs": [{"x": 0, "y": 0, "cost": 2.0}],
+                "cost_regions": [{
+                    "polygon": [{"x": 4.0, "y": 0.0}, {"x": 6.0, "y": 0.0}, {"x": 6.0, "y": 2.0}, {"x": 4.0, "y": 2.0}],
+                    "cost": 0.5
+                }],
+                "zones": [{
+                    "name": "source",
+                    "polygon": [{"x": 8.0, "y": 0.0}, {"x": 9.0, "y": 0.0}, {"x": 9.0, "y": 1.0}],
+                    "approach_poses": [{"x": 8.5, "y": 1.5, "heading": 1.5}]
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(map.get_zone("source").is_some());
+        assert!(map.get_zone("speaker").is_none());
+
+        let grid = map.build_grid();
+        assert_eq!(grid.get_size(), Vector2::new(20, 10));
+        assert!(grid.is_obstructed(grid.meters_to_cell(Vector2::new(1.8, 1.2))));
+        assert!(!grid.is_obstructed(grid.meters_to_cell(Vector2::new(5.0, 4.0))));
+        assert_eq!(grid.get_cell_cost(Vector2::new(0, 0)), 2.0);
+        assert_eq!(
+            grid.get_cell_cost(grid.meters_to_cell(Vector2::new(5.0, 1.0))),
+            0.5
+        );
+        assert_eq!(grid.get_cell_costs().len(), 17);
+
+        let zone = grid.get_semantic_zone("source").unwrap();
+        assert_eq!(zone.approach_poses[0].heading, 1.5);
+        assert_eq!(
+            grid.get_semantic_zones_at(Vector2::new(8.9, 0.5))[0].name,
+            "source"
+        );
+        assert!(grid
+            .get_semantic_zones_at(Vector2::new(8.1, 0.5))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_invalid_maps() {
+        let cases = [
+            r#"{"square_size_meters": 0.5, "obstacles": []}"#,
+            r#"{"square_size_meters": 0.0, "grid_size_pixels": 10}"#,
+            r#"{"square_size_meters": 0.5, "grid_size_pixels": 10, "obstacles": [{"x": 10, "y": 0}]}"#,
+            r#"{"square_size_meters": 0.5, "grid_size_pixels": 10, "polygons": [[{"x": 1, "y": 1}]]}"#,
+            r#"{"square_size_meters": 0.5, "grid_size_pixels": 10,
+                "cell_costs": [{"x": 0, "y": -1, "cost": 1.0}]}"#,
+            r#"{"square_size_meters": 0.5, "grid_size_pixels": 10, "zones": [
+                {"name": "a", "polygon": [{"x": 0, "y": 0}, {"x": 1, "y": 0}, {"x": 1, "y": 1}]},
+                {"name": "a", "polygon": [{"x": 0, "y": 0}, {"x": 1, "y": 0}, {"x": 1, "y": 1}]}
+            ]}"#,
+        ];
+
+        for case in cases {
+            assert!(matches!(
+                FieldMap::from_json(case),
+                Err(MapError::Invalid(_))
+            ));
+        }
+        assert!(matches!(FieldMap::from_json("{"), Err(MapError::Json(_))));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut grid = HybridGrid::new(
+            12,
+            8,
+            0.25,
+            vec![Vector2::new(2, 3), Vector2::new(1, 1)],
+            4,
+            4,
+        );
+        grid.set_cell_cost(Vector2::new(3, 3), 1.5);
+        grid.set_unknown_cells(&[Vector2::new(5, 5)], true);
+        grid.set_semantic_zone(
+            SemanticZone::new(
+                "station",
+                vec![
+                    Vector2::new(0.0, 0.0),
+                    Vector2::new(1.0, 0.0),
+                    Vector2::new(1.0, 1.0),
+                ],
+            )
+            .with_approach_poses(vec![ApproachPose::new(Vector2::new(1.5, 0.5), 3.0)]),
+        );
+
+        let map = FieldMap::from_grid(&grid);
+        let loaded = FieldMap::from_json(&map.to_json().unwrap()).unwrap();
+        assert_eq!(loaded, map);
+
+        let rebuilt = loaded.build_grid();
+        assert_eq!(rebuilt.get_size(), grid.get_size());
+        assert_eq!(rebuilt.get_center(), grid.get_center());
+        assert_eq!(rebuilt.get_static_obstacles(), grid.get_static_obstacles());
+        assert_eq!(rebuilt.get_cell_costs(), grid.get_cell_costs());
+        assert_eq!(rebuilt.get_unknown_cells(), grid.get_unknown_cells());
+        assert_eq!(rebuilt.get_semantic_zones(), grid.get_semantic_zones());
+    }
+}
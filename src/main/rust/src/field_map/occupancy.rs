@@ -0,0 +1,387 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use image::{GrayImage, Luma};
+use nalgebra::Vector2;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{invalid, MapError};
+use crate::hybrid_grid::HybridGrid;
+
+/// The gray levels written on export, the same ones the ROS map saver uses.
+const OCCUPIED_PIXEL: u8 = 0;
+const FREE_PIXEL: u8 = 254;
+const UNKNOWN_PIXEL: u8 = 205;
+
+/// How gray levels between the free and occupied thresholds are read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OccupancyMode {
+    /// In between levels are unknown
+    #[default]
+    Trinary,
+    /// In between levels are free with a traversal cost from 0 (free threshold) to 1 (occupied
+    /// threshold)
+    Scale,
+}
+
+/// The contents of a ROS `map.yaml`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OccupancyMetadata {
+    /// The image file, relative to the yaml file
+    pub image: String,
+    /// The size of one pixel in meters
+    pub resolution: f32,
+    /// The position of the lower left pixel in meters and the map's yaw, which has to be 0
+    pub origin: [f32; 3],
+    #[serde(default = "get_default_occupied_thresh")]
+    pub occupied_thresh: f32,
+    #[serde(default = "get_default_free_thresh")]
+    pub free_thresh: f32,
+    /// Whether white means occupied instead of free
+    #[serde(
+        default,
+        serialize_with = "serialize_flag",
+        deserialize_with = "deserialize_flag"
+    )]
+    pub negate: bool,
+    #[serde(default)]
+    pub mode: OccupancyMode,
+}
+
+fn get_default_occupied_thresh() -> f32 {
+    0.65
+}
+
+fn get_default_free_thresh() -> f32 {
+    0.196
+}
+
+/// ROS writes `negate` as 0 or 1.
+fn serialize_flag<S: Serializer>(flag: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u8(*flag as u8)
+}
+
+fn deserialize_flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Int(i64),
+    }
+
+    Ok(match Flag::deserialize(deserializer)? {
+        Flag::Bool(flag) => flag,
+        Flag::Int(flag) => flag != 0,
+    })
+}
+
+impl OccupancyMetadata {
+    pub fn validate(&self) -> Result<(), MapError> {
+        if !self.resolution.is_finite() || self.resolution <= 0.0 {
+            return Err(invalid(format!(
+                "resolution must be positive, got {}",
+                self.resolution
+            )));
+        }
+
+        if !(0.0..=1.0).contains(&self.free_thresh)
+            || !(0.0..=1.0).contains(&self.occupied_thresh)
+            || self.free_thresh > self.occupied_thresh
+        {
+            return Err(invalid(format!(
+                "thresholds must satisfy 0 <= free ({}) <= occupied ({}) <= 1",
+                self.free_thresh, self.occupied_thresh
+            )));
+        }
+
+        if self.origin[2].abs() > f32::EPSILON {
+            return Err(invalid("rotated maps (origin yaw != 0) are not supported"));
+        }
+
+        Ok(())
+    }
+
+    /// # Returns
+    /// How likely a pixel of gray level `value` is occupied, from 0 to 1.
+    fn get_occupancy(&self, value: u8) -> f32 {
+        let value = value as f32 / 255.0;
+        if self.negate {
+            value
+        } else {
+            1.0 - value
+        }
+    }
+
+    fn get_pixel(&self, occupancy: f32) -> u8 {
+        let value = if self.negate {
+            occupancy
+        } else {
+            1.0 - occupancy
+        };
+        (value * 255.0).round() as u8
+    }
+}
+
+/// A grid read from or written to an occupancy image. Gray cells read in `OccupancyMode::Scale`
+/// become cell costs from 0 to 1.
+pub struct OccupancyMap {
+    pub grid: HybridGrid,
+}
+
+impl OccupancyMap {
+    pub fn new(grid: HybridGrid) -> Self {
+        Self { grid }
+    }
+
+    /// # Returns
+    /// The map described by the ROS map yaml at `path` and the image it points to.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MapError> {
+        let path = path.as_ref();
+        let metadata: OccupancyMetadata = serde_yaml::from_str(&fs::read_to_string(path)?)?;
+        let image_path = path.parent().unwrap_or(Path::new("")).join(&metadata.image);
+
+        Self::from_image(&metadata, &image::open(image_path)?.to_luma8())
+    }
+
+    /// # Returns
+    /// The map of `image`, whose top row is the far end of the y axis as in ROS.
+    /// # Arguments
+    /// * `metadata` - How to place and read the image, `image` is ignored
+    /// * `image` - The gray levels of the map
+    pub fn from_image(metadata: &OccupancyMetadata, image: &GrayImage) -> Result<Self, MapError> {
+        metadata.validate()?;
+
+        let (width, height) = image.dimensions();
+        let origin = Vector2::new(
+            (metadata.origin[0] / metadata.resolution).round() as i32,
+            (metadata.origin[1] / metadata.resolution).round() as i32,
+        );
+        // Grids can only be an even number of cells wide, odd images get a free extra column or row
+        let size = Vector2::new(
+            width as i32 + width as i32 % 2,
+            height as i32 + height as i32 % 2,
+        );
+        let center = origin + size / 2;
+
+        let mut obstacles = Vec::new();
+        let mut costs: HashMap<u8, Vec<Vector2<i32>>> = HashMap::new();
+        let mut unknown = Vec::new();
+        for (column, row, pixel) in image.enumerate_pixels() {
+            let cell = origin + Vector2::new(column as i32, (height - 1 - row) as i32);
+            let occupancy = metadata.get_occupancy(pixel[0]);
+
+            if occupancy > metadata.occupied_thresh {
+                obstacles.push(cell);
+            } else if occupancy >= metadata.free_thresh {
+                match metadata.mode {
+                    OccupancyMode::Trinary => unknown.push(cell),
+                    OccupancyMode::Scale => {
+                        // Keyed by gray level so each level is set as one change
+                        costs.entry(pixel[0]).or_default().push(cell);
+                    }
+                }
+            }
+        }
+
+        let mut grid = HybridGrid::new(
+            size.x,
+            size.y,
+            metadata.resolution,
+            obstacles,
+            center.x,
+            center.y,
+        );
+        let range = metadata.occupied_thresh - metadata.free_thresh;
+        for (value, cells) in costs {
+            let occupancy = metadata.get_occupancy(value);
+            let cost = if range > 0.0 {
+                (occupancy - metadata.free_thresh) / range
+            } else {
+                1.0
+            };
+            grid.set_cell_costs(&cells, cost);
+        }
+
+        grid.set_unknown_cells(&unknown, true);
+
+        Ok(Self { grid })
+    }
+
+    /// # Returns
+    /// The metadata describing `to_image`, in scale mode if there are any costs to keep.
+    /// # Arguments
+    /// * `image` - The name of the image file, relative to the yaml file
+    pub fn get_metadata(&self, image: &str) -> OccupancyMetadata {
+        let (min, _) = self.grid.get_cell_bounds();
+        let resolution = self.grid.get_square_size_meters();
+
+        OccupancyMetadata {
+            image: image.to_string(),
+            resolution,
+            origin: [min.x as f32 * resolution, min.y as f32 * resolution, 0.0],
+            occupied_thresh: get_default_occupied_thresh(),
+            free_thresh: get_default_free_thresh(),
+            negate: false,
+            mode: if self.grid.get_cell_costs().is_empty() {
+                OccupancyMode::Trinary
+            } else {
+                OccupancyMode::Scale
+            },
+        }
+    }
+
+    /// # Returns
+    /// The grid drawn as an image: obstacles black, free cells white, unknown cells gray and costs
+    /// as the gray levels they were read from, saturating at 1. A grid with costs is described in
+    /// scale mode, which has no unknown state, so its unknown cells read back as free cells with a
+    /// cost near 0.
+    pub fn to_image(&self) -> GrayImage {
+        let metadata = self.get_metadata("");
+        let (min, max) = self.grid.get_cell_bounds();
+        let size = max - min + Vector2::repeat(1);
+
+        let mut image = GrayImage::from_pixel(size.x as u32, size.y as u32, Luma([FREE_PIXEL]));
+        let mut draw = |cell: &Vector2<i32>, value: u8| {
+            if !self.grid.is_outside_grid(*cell) {
+                image.put_pixel(
+                    (cell.x - min.x) as u32,
+                    (max.y - cell.y) as u32,
+                    Luma([value]),
+                );
+            }
+        };
+
+        for cell in self.grid.get_unknown_cells() {
+            draw(cell, UNKNOWN_PIXEL);
+        }
+        for (cell, cost) in self.grid.get_cell_costs() {
+            let occupancy = metadata.free_thresh
+                + cost.clamp(0.0, 1.0) * (metadata.occupied_thresh - metadata.free_thresh);
+            draw(cell, metadata.get_pixel(occupancy));
+        }
+        for cell in self.grid.get_static_obstacles() {
+            draw(cell, OCCUPIED_PIXEL);
+        }
+
+        image
+    }
+
+    /// Writes the map yaml to `path` and the image next to it, in the format its extension names
+    /// (`.pgm` or `.png`). Unknown cells of a grid with costs are lost, see `to_image`.
+    pub fn save(&self, path: impl AsRef<Path>, image: &str) -> Result<(), MapError> {
+        let path = path.as_ref();
+        let metadata = self.get_metadata(image);
+
+        self.to_image()
+            .save(path.parent().unwrap_or(Path::new("")).join(&metadata.image))?;
+        fs::write(path, serde_yaml::to_string(&metadata)?)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_metadata(mode: OccupancyMode) -> OccupancyMetadata {
+        OccupancyMetadata {
+            image: "map.pgm".to_string(),
+            resolution: 0.5,
+            origin: [-1.0, 2.0, 0.0],
+            occupied_thresh: 0.65,
+            free_thresh: 0.196,
+            negate: false,
+            mode,
+        }
+    }
+
+    #[test]
+    fn test_image_orientation() {
+        let mut image = GrayImage::from_pixel(4, 3, Luma([FREE_PIXEL]));
+        image.put_pixel(0, 0, Luma([OCCUPIED_PIXEL]));
+        image.put_pixel(3, 2, Luma([UNKNOWN_PIXEL]));
+
+        let map =
+            OccupancyMap::from_image(&create_metadata(OccupancyMode::Trinary), &image).unwrap();
+
+        // The top left pixel is the lowest x and highest y
+        assert!(map.grid.is_obstructed(Vector2::new(-2, 6)));
+        assert_eq!(map.grid.get_static_obstacles().len(), 1);
+        assert!(map.grid.is_unknown(Vector2::new(1, 4)));
+        assert_eq!(map.grid.get_unknown_cells().len(), 1);
+        assert!(!map.grid.is_outside_grid(Vector2::new(-2, 4)));
+        assert!(!map.grid.is_outside_grid(Vector2::new(1, 6)));
+    }
+
+    #[test]
+    fn test_scale_mode_keeps_costs() {
+        let mut image = GrayImage::from_pixel(2, 2, Luma([FREE_PIXEL]));
+        image.put_pixel(1, 1, Luma([150]));
+
+        let mut metadata = create_metadata(OccupancyMode::Scale);
+        let map = OccupancyMap::from_image(&metadata, &image).unwrap();
+        let cost = map.grid.get_cell_cost(Vector2::new(-1, 4));
+        assert!(cost > 0.0 && cost < 1.0);
+        assert!(map.grid.get_unknown_cells().is_empty());
+
+        metadata.negate = true;
+        let map = OccupancyMap::from_image(&metadata, &image).unwrap();
+        assert_eq!(map.grid.get_static_obstacles().len(), 3);
+    }
+
+    #[test]
+    fn test_invalid_metadata() {
+        let image = GrayImage::new(2, 2);
+
+        let mut metadata = create_metadata(OccupancyMode::Trinary);
+        metadata.origin[2] = 0.5;
+        assert!(OccupancyMap::from_image(&metadata, &image).is_err());
+
+        let mut metadata = create_metadata(OccupancyMode::Trinary);
+        metadata.free_thresh = 0.9;
+        assert!(OccupancyMap::from_image(&metadata, &image).is_err());
+
+        let yaml = "image: map.pgm\nresolution: 0.05\norigin: [0.0, 0.0, 0.0]\nnegate: 1\n";
+        let metadata: OccupancyMetadata = serde_yaml::from_str(yaml).unwrap();
+        assert!(metadata.negate);
+        assert_eq!(metadata.mode, OccupancyMode::Trinary);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut map = OccupancyMap::new(HybridGrid::new(
+            6,
+            4,
+            0.25,
+            vec![Vector2::new(0, 0), Vector2::new(5, 3)],
+            3,
+            2,
+        ));
+        map.grid.set_cell_cost(Vector2::new(2, 1), 0.5);
+        map.grid.set_unknown_cells(&[Vector2::new(4, 0)], true);
+
+        let directory = std::env::temp_dir().join(format!("occupancy_test_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        for image in ["map.pgm", "map.png"] {
+            map.save(directory.join("map.yaml"), image).unwrap();
+            let loaded = OccupancyMap::load(directory.join("map.yaml")).unwrap();
+
+            assert_eq!(loaded.grid.get_size(), map.grid.get_size());
+            assert_eq!(loaded.grid.get_center(), map.grid.get_center());
+            assert_eq!(
+                loaded.grid.get_static_obstacles(),
+                map.grid.get_static_obstacles()
+            );
+            assert!((loaded.grid.get_cell_cost(Vector2::new(2, 1)) - 0.5).abs() < 0.02);
+            // Scale mode has no unknown cells, they come back as costs as documented on `to_image`
+            assert!(loaded
+                .grid
+                .get_cell_costs()
+                .contains_key(&Vector2::new(4, 0)));
+        }
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+use super::{FieldMap, MapError};
+use crate::hybrid_grid::HybridGrid;
+
+/// The fields of recent FRC games, shipped as map files in `presets/`. Each has its boundary
+/// walls, the permanent structures robots can't drive through and named zones for both
+/// alliances ("blue_..." and "red_..."), all in meters so any resolution can be built.
+///
+/// The geometry follows `orientation.txt`: the blue alliance wall is at y = 0 and forward (+y)
+/// points towards the red alliance wall. It is simplified from the official drawings and meant
+/// for planning, not for precise alignment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldPreset {
+    Crescendo2024,
+    Reefscape2025,
+}
+
+impl FieldPreset {
+    pub const ALL: [FieldPreset; 2] = [FieldPreset::Crescendo2024, FieldPreset::Reefscape2025];
+
+    /// # Returns
+    /// The name of the preset, which is also the name of its map file.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            FieldPreset::Crescendo2024 => "crescendo_2024",
+            FieldPreset::Reefscape2025 => "reefscape_2025",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.get_name() == name)
+    }
+
+    fn get_json(&self) -> &'static str {
+        match self {
+            FieldPreset::Crescendo2024 => include_str!("../../presets/crescendo_2024.json"),
+            FieldPreset::Reefscape2025 => include_str!("../../presets/reefscape_2025.json"),
+        }
+    }
+
+    /// # Returns
+    /// The preset's map at the resolution it is stored in.
+    pub fn get_map(&self) -> FieldMap {
+        FieldMap::from_json(self.get_json()).expect("Preset maps are valid")
+    }
+
+    /// # Returns
+    /// The preset's field as a grid with cells of `square_size_meters`, or why that resolution
+    /// can't be used.
+    pub fn build_grid(&self, square_size_meters: f32) -> Result<HybridGrid, MapError> {
+        Ok(self
+            .get_map()
+            .with_resolution(square_size_meters)?
+            .build_grid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use super::*;
+    use crate::hybrid_grid::alliance::{AllianceTransform, FieldSymmetry};
+
+    #[test]
+    fn test_presets() {
+        for preset in FieldPreset::ALL {
+            assert_eq!(FieldPreset::from_name(preset.get_name()), Some(preset));
+
+            let coarse = preset.build_grid(0.2).unwrap();
+            let fine = preset.build_grid(0.05).unwrap();
+            assert!(coarse.get_size().x * 4 >= fine.get_size().x);
+            assert!(coarse.get_size().x * 3 < fine.get_size().x);
+            assert!(coarse.is_obstructed(Vector2::new(0, 10)));
+            assert!(fine.is_obstructed(Vector2::new(40, 0)));
+
+            // Every approach pose can be stood on
+            for zone in fine.get_semantic_zones().values() {
+                for pose in &zone.approach_poses {
+                    let cell = fine.meters_to_cell(pose.position);
+                    assert!(!fine.is_obstructed(cell), "{} is blocked", zone.name);
+                }
+            }
+        }
+
+        assert!(FieldPreset::Reefscape2025.build_grid(0.0).is_err());
+        assert!(FieldPreset::from_name("steamworks_2017").is_none());
+    }
+
+    #[test]
+    fn test_preset_symmetry() {
+        let grid = FieldPreset::Reefscape2025.build_grid(0.1).unwrap();
+        let transform = AllianceTransform::new(&grid, FieldSymmetry::Rotational);
+
+        let blue = grid.get_semantic_zone("blue_reef_face_A").unwrap();
+        let red = grid.get_semantic_zone("red_reef_face_A").unwrap();
+        let flipped = transform.flip_meters(blue.approach_poses[0].position);
+        assert!((flipped - red.approach_poses[0].position).magnitude() < 0.2);
+        assert_eq!(
+            grid.get_semantic_zones_at(flipped)[0].name,
+            "red_reef_face_A"
+        );
+    }
+}
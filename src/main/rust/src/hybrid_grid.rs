@@ -1,14 +1,31 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     num::NonZero,
     sync::Arc,
 };
 
+use heatmap::Heatmap;
+use hybrid_objects::{HybridObject, ObjectClass};
+use keep_out::{KeepOutZone, TimeWindow};
 use kiddo::{KdTree, NearestNeighbour};
+use log_odds::{LogOddsConfig, LogOddsGrid};
 use nalgebra::{Matrix3, Vector2};
+use shapes::{Coverage, ObstacleShape};
+use uncertainty_fields::{Falloff, FieldCombine, FieldShape, UncertentyField};
+use zones::SemanticZone;
 
+pub mod alliance;
 pub mod dynamic_objects;
+pub mod heatmap;
+pub mod hybrid_objects;
+pub mod keep_out;
+pub mod log_odds;
 pub mod math;
+pub mod rolling_window;
+pub mod shapes;
+pub mod shared_grid;
+pub mod uncertainty_fields;
+pub mod zones;
 
 pub trait GenericDynamicObject: Send + Sync {
     fn calculate_transformation_matrix_at(&self, time: f64) -> Matrix3<f64>;
@@ -26,11 +43,25 @@ impl GenericDynamicObject for Arc<dyn GenericDynamicObject> {
     }
 }
 
-#[derive(Clone)]
-pub struct UncertentyField {
-    pub center: Vector2<f32>,
-    pub radius: f32,
-    pub intensity: f32,
+/// How many dirty regions are remembered before the oldest ones are dropped.
+const MAX_DIRTY_REGIONS: usize = 256;
+
+/// A rectangle of cells (inclusive on both corners) whose static obstacles or cell costs changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DirtyRegion {
+    pub min: Vector2<i32>,
+    pub max: Vector2<i32>,
+    /// The grid version the change produced
+    pub version: u64,
+}
+
+/// What is known about a cell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CellState {
+    Free,
+    Occupied,
+    /// Nothing has been observed there yet
+    Unknown,
 }
 
 #[derive(Clone)]
@@ -42,10 +73,39 @@ pub struct HybridGrid {
     square_size_meters: f32,
 
     static_obstacles: HashSet<Vector2<i32>>,
+    /// Cells that are neither known free nor known occupied, never static obstacles at the same
+    /// time
+    unknown_cells: HashSet<Vector2<i32>>,
+    /// The positions of `hybrid_objects`, keyed by their IDs
     hybrid_obstacles: KdTree<f32, 2>,
+    hybrid_objects: HashMap<u64, HybridObject>,
+    next_hybrid_object_id: u64,
+    /// Never shrinks until the objects are cleared, only used to widen searches
+    max_hybrid_object_radius: f32,
+    /// Match time in seconds, which hybrid objects expire against
+    time: f64,
+    /// Where opponent robots have been over the match
+    opponent_heatmap: Heatmap,
+    keep_out_zones: HashMap<u64, KeepOutZone>,
+    next_keep_out_zone_id: u64,
+    /// Named areas of the field, keyed by their names
+    semantic_zones: HashMap<String, SemanticZone>,
+    /// Obstacles believed in from sensor observations, kept apart from the static map so that
+    /// clearing rays never erase it
+    observations: LogOddsGrid,
+    /// Extra traversal cost of passable cells, as a multiple of the distance travelled in them
+    cell_costs: HashMap<Vector2<i32>, f32>,
+
+    version: u64,
+    dirty_regions: VecDeque<DirtyRegion>,
+    /// The newest version whose dirty region was dropped from `dirty_regions`
+    dirty_regions_dropped_version: u64,
 
     uncertenty_defs: HashMap<u64, UncertentyField>,
+    /// The centers of `uncertenty_defs`, keyed by their IDs
     uncertenty_fields: KdTree<f32, 2>,
+    next_uncertenty_field_id: u64,
+    /// The largest bounding radius of any field, never shrinking until the fields are cleared
     max_field_radius: f32,
 }
 
@@ -64,9 +124,24 @@ impl HybridGrid {
             center_y,
             square_size_meters,
             static_obstacles: HashSet::new(),
+            unknown_cells: HashSet::new(),
             hybrid_obstacles: KdTree::new(),
+            hybrid_objects: HashMap::new(),
+            next_hybrid_object_id: 0,
+            max_hybrid_object_radius: 0.0,
+            time: 0.0,
+            opponent_heatmap: Heatmap::default(),
+            keep_out_zones: HashMap::new(),
+            next_keep_out_zone_id: 0,
+            semantic_zones: HashMap::new(),
+            observations: LogOddsGrid::default(),
+            cell_costs: HashMap::new(),
+            version: 0,
+            dirty_regions: VecDeque::new(),
+            dirty_regions_dropped_version: 0,
             uncertenty_defs: HashMap::new(),
             uncertenty_fields: KdTree::new(),
+            next_uncertenty_field_id: 1,
             max_field_radius: 0.0,
         }
     }
@@ -83,7 +158,7 @@ impl HybridGrid {
 
         for obstacle in static_obstacles {
             if !grid.is_outside_grid(obstacle) {
-                grid.push_static_obstacle(obstacle);
+                grid.static_obstacles.insert(obstacle);
             }
         }
 
@@ -94,8 +169,246 @@ impl HybridGrid {
         self.square_size_meters
     }
 
+    /// # Returns
+    /// The number of cells on both axes.
+    pub fn get_size(&self) -> Vector2<i32> {
+        Vector2::new(self.size_x, self.size_y)
+    }
+
+    pub fn get_center(&self) -> Vector2<i32> {
+        Vector2::new(self.center_x, self.center_y)
+    }
+
+    /// Moves the grid so it is centered on `center`, forgetting the static obstacles, unknown
+    /// cells, cell costs and observations that end up outside. Hybrid objects and uncertainty
+    /// fields are left alone.
+    /// # Returns
+    /// How many cells were forgotten.
+    pub fn set_center(&mut self, center: Vector2<i32>) -> usize {
+        if center == self.get_center() {
+            return 0;
+        }
+
+        self.center_x = center.x;
+        self.center_y = center.y;
+        let (min, max) = self.get_cell_bounds();
+        let is_inside = |cell: &Vector2<i32>| {
+            cell.x >= min.x && cell.x <= max.x && cell.y >= min.y && cell.y <= max.y
+        };
+
+        let before = self.static_obstacles.len() + self.unknown_cells.len() + self.cell_costs.len();
+        self.static_obstacles.retain(is_inside);
+        self.unknown_cells.retain(is_inside);
+        self.cell_costs.retain(|cell, _| is_inside(cell));
+        self.observations.retain(is_inside);
+
+        self.mark_dirty(min, max);
+        before - self.static_obstacles.len() - self.unknown_cells.len() - self.cell_costs.len()
+    }
+
+    /// Static obstacle edits make the edited cells known, so they stop being unknown.
     pub fn push_static_obstacle(&mut self, obstacle: Vector2<i32>) {
-        self.static_obstacles.insert(obstacle);
+        self.set_static_obstacles(&[obstacle], true);
+    }
+
+    /// # Returns
+    /// Whether there was a static obstacle at `obstacle` to remove.
+    pub fn remove_static_obstacle(&mut self, obstacle: Vector2<i32>) -> bool {
+        let removed = self.static_obstacles.contains(&obstacle);
+        self.set_static_obstacles(&[obstacle], false);
+
+        removed
+    }
+
+    /// Adds or removes many static obstacles as a single change. Every position given becomes
+    /// known, occupied or free.
+    /// # Returns
+    /// How many positions actually changed.
+    pub fn set_static_obstacles(&mut self, positions: &[Vector2<i32>], obstructed: bool) -> usize {
+        let changed: Vec<Vector2<i32>> = positions
+            .iter()
+            .copied()
+            .filter(|position| {
+                let was_unknown = self.unknown_cells.remove(position);
+                let changed = if obstructed {
+                    self.static_obstacles.insert(*position)
+                } else {
+                    self.static_obstacles.remove(position)
+                };
+
+                changed || was_unknown
+            })
+            .collect();
+
+        if let Some((min, max)) = get_position_bounds(&changed) {
+            self.mark_dirty(min, max);
+        }
+
+        changed.len()
+    }
+
+    /// Removes every static obstacle in the rectangle from `min` to `max` (inclusive).
+    /// # Returns
+    /// How many obstacles were removed.
+    pub fn clear_static_region(&mut self, min: Vector2<i32>, max: Vector2<i32>) -> usize {
+        let before = self.static_obstacles.len();
+        self.static_obstacles.retain(|obstacle| {
+            obstacle.x < min.x || obstacle.x > max.x || obstacle.y < min.y || obstacle.y > max.y
+        });
+
+        let removed = before - self.static_obstacles.len();
+        if removed > 0 {
+            self.mark_dirty(min, max);
+        }
+
+        removed
+    }
+
+    /// Marks many cells as unknown, or as known free, as a single change. Unknown cells lose their
+    /// static obstacle.
+    /// # Returns
+    /// How many positions actually changed.
+    pub fn set_unknown_cells(&mut self, positions: &[Vector2<i32>], unknown: bool) -> usize {
+        let changed: Vec<Vector2<i32>> = positions
+            .iter()
+            .copied()
+            .filter(|position| {
+                if unknown {
+                    let was_obstructed = self.static_obstacles.remove(position);
+                    self.unknown_cells.insert(*position) || was_obstructed
+                } else {
+                    self.unknown_cells.remove(position)
+                }
+            })
+            .collect();
+
+        if let Some((min, max)) = get_position_bounds(&changed) {
+            self.mark_dirty(min, max);
+        }
+
+        changed.len()
+    }
+
+    pub fn is_unknown(&self, position: Vector2<i32>) -> bool {
+        self.unknown_cells.contains(&position)
+    }
+
+    pub fn get_unknown_cells(&self) -> &HashSet<Vector2<i32>> {
+        &self.unknown_cells
+    }
+
+    pub fn get_cell_state(&self, position: Vector2<i32>) -> CellState {
+        if self.static_obstacles.contains(&position) {
+            CellState::Occupied
+        } else if self.unknown_cells.contains(&position) {
+            CellState::Unknown
+        } else {
+            CellState::Free
+        }
+    }
+
+    /// # Returns
+    /// The share of the steps of `path` that end in an unknown cell, from 0 to 1. The start is left
+    /// out since it is where the robot already is.
+    pub fn get_unknown_share(&self, path: &[Vector2<i32>]) -> f64 {
+        if path.len() < 2 {
+            return 0.0;
+        }
+
+        let unknown = path[1..]
+            .iter()
+            .filter(|position| self.is_unknown(**position))
+            .count();
+        unknown as f64 / (path.len() - 1) as f64
+    }
+
+    /// Sets the traversal cost of many cells as a single change. Crossing a cell with cost `c`
+    /// costs `1 + c` times the distance, so 1 makes it as expensive as a one cell detour per cell.
+    /// # Returns
+    /// How many cells actually changed.
+    /// # Arguments
+    /// * `positions` - The cells to change
+    /// * `cost` - The new cost, 0 or less makes the cells free to cross again
+    pub fn set_cell_costs(&mut self, positions: &[Vector2<i32>], cost: f32) -> usize {
+        let changed: Vec<Vector2<i32>> = positions
+            .iter()
+            .copied()
+            .filter(|position| {
+                if cost > 0.0 {
+                    self.cell_costs.insert(*position, cost) != Some(cost)
+                } else {
+                    self.cell_costs.remove(position).is_some()
+                }
+            })
+            .collect();
+
+        if let Some((min, max)) = get_position_bounds(&changed) {
+            self.mark_dirty(min, max);
+        }
+
+        changed.len()
+    }
+
+    pub fn set_cell_cost(&mut self, position: Vector2<i32>, cost: f32) {
+        self.set_cell_costs(&[position], cost);
+    }
+
+    /// # Returns
+    /// The traversal cost of `position`, 0 for cells without one.
+    pub fn get_cell_cost(&self, position: Vector2<i32>) -> f32 {
+        self.cell_costs.get(&position).copied().unwrap_or(0.0)
+    }
+
+    pub fn get_cell_costs(&self) -> &HashMap<Vector2<i32>, f32> {
+        &self.cell_costs
+    }
+
+    pub fn clear_cell_costs(&mut self) {
+        if let Some((min, max)) =
+            get_position_bounds(&self.cell_costs.keys().copied().collect::<Vec<_>>())
+        {
+            self.cell_costs.clear();
+            self.mark_dirty(min, max);
+        }
+    }
+
+    /// # Returns
+    /// The version of the static obstacles and cell costs, which increases with every change to
+    /// them.
+    pub fn get_version(&self) -> u64 {
+        self.version
+    }
+
+    /// # Returns
+    /// Every region that changed after `version`, oldest first, or `None` if some of those changes
+    /// are no longer remembered and everything has to be treated as changed.
+    pub fn get_dirty_regions_since(&self, version: u64) -> Option<Vec<DirtyRegion>> {
+        if version < self.dirty_regions_dropped_version {
+            return None;
+        }
+
+        Some(
+            self.dirty_regions
+                .iter()
+                .filter(|region| region.version > version)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    fn mark_dirty(&mut self, min: Vector2<i32>, max: Vector2<i32>) {
+        self.version += 1;
+        self.dirty_regions.push_back(DirtyRegion {
+            min,
+            max,
+            version: self.version,
+        });
+
+        if self.dirty_regions.len() > MAX_DIRTY_REGIONS {
+            if let Some(dropped) = self.dirty_regions.pop_front() {
+                self.dirty_regions_dropped_version = dropped.version;
+            }
+        }
     }
 
     pub fn get_static_obstacles(&self) -> &HashSet<Vector2<i32>> {
@@ -112,17 +425,401 @@ impl HybridGrid {
             || position.y >= self.center_y + half_size_y
     }
 
+    /// # Returns
+    /// The first and last cell inside the grid on both axes (inclusive).
+    pub fn get_cell_bounds(&self) -> (Vector2<i32>, Vector2<i32>) {
+        let half_size_x = self.size_x / 2;
+        let half_size_y = self.size_y / 2;
+
+        (
+            Vector2::new(self.center_x - half_size_x, self.center_y - half_size_y),
+            Vector2::new(
+                self.center_x + half_size_x - 1,
+                self.center_y + half_size_y - 1,
+            ),
+        )
+    }
+
+    /// # Returns
+    /// The cell containing `position`, given in field meters. Cell (x, y) spans from
+    /// (x, y) * square size to (x + 1, y + 1) * square size.
+    pub fn meters_to_cell(&self, position: Vector2<f32>) -> Vector2<i32> {
+        Vector2::new(
+            (position.x / self.square_size_meters).floor() as i32,
+            (position.y / self.square_size_meters).floor() as i32,
+        )
+    }
+
+    /// # Returns
+    /// The center of `cell` in field meters.
+    pub fn cell_to_meters(&self, cell: Vector2<i32>) -> Vector2<f32> {
+        Vector2::new(
+            (cell.x as f32 + 0.5) * self.square_size_meters,
+            (cell.y as f32 + 0.5) * self.square_size_meters,
+        )
+    }
+
+    /// Rasterizes `shape` into static obstacles as a single change.
+    /// # Returns
+    /// How many cells became obstacles.
+    pub fn add_obstacle_shape(&mut self, shape: &ObstacleShape, coverage: Coverage) -> usize {
+        let cells = shape.rasterize(self, coverage);
+        self.set_static_obstacles(&cells, true)
+    }
+
+    /// Rasterizes `shape` into cells of traversal cost `cost` as a single change.
+    /// # Returns
+    /// How many cells changed.
+    pub fn add_cost_shape(
+        &mut self,
+        shape: &ObstacleShape,
+        coverage: Coverage,
+        cost: f32,
+    ) -> usize {
+        let cells = shape.rasterize(self, coverage);
+        self.set_cell_costs(&cells, cost)
+    }
+
+    /// Adds a zone that may not be entered while it is active. Cells outside the grid are dropped.
+    /// # Returns
+    /// The ID of the new zone.
+    pub fn add_keep_out_zone(&mut self, mut zone: KeepOutZone) -> u64 {
+        zone.cells.retain(|cell| !self.is_outside_grid(*cell));
+
+        let id = self.next_keep_out_zone_id;
+        self.next_keep_out_zone_id += 1;
+        self.keep_out_zones.insert(id, zone);
+
+        id
+    }
+
+    /// Rasterizes `shape` into a keep-out zone active during `windows`.
+    /// # Returns
+    /// The ID of the new zone.
+    pub fn add_keep_out_shape(
+        &mut self,
+        shape: &ObstacleShape,
+        coverage: Coverage,
+        windows: Vec<TimeWindow>,
+    ) -> u64 {
+        let cells = shape.rasterize(self, coverage);
+        self.add_keep_out_zone(KeepOutZone::new(cells, windows))
+    }
+
+    /// # Returns
+    /// The removed zone, if there was one with that ID.
+    pub fn remove_keep_out_zone(&mut self, id: u64) -> Option<KeepOutZone> {
+        self.keep_out_zones.remove(&id)
+    }
+
+    pub fn get_keep_out_zones(&self) -> &HashMap<u64, KeepOutZone> {
+        &self.keep_out_zones
+    }
+
+    pub fn clear_keep_out_zones(&mut self) {
+        self.keep_out_zones.clear();
+    }
+
+    /// # Returns
+    /// Whether `position` is in a keep-out zone that is active at match time `time`.
+    pub fn is_kept_out(&self, position: Vector2<i32>, time: f64) -> bool {
+        self.keep_out_zones
+            .values()
+            .any(|zone| zone.is_kept_out(position, time))
+    }
+
+    /// Adds a named zone, replacing any zone with the same name.
+    /// # Returns
+    /// The zone that was replaced.
+    pub fn set_semantic_zone(&mut self, zone: SemanticZone) -> Option<SemanticZone> {
+        self.semantic_zones.insert(zone.name.clone(), zone)
+    }
+
+    /// # Returns
+    /// The removed zone, if there was one with that name.
+    pub fn remove_semantic_zone(&mut self, name: &str) -> Option<SemanticZone> {
+        self.semantic_zones.remove(name)
+    }
+
+    pub fn get_semantic_zone(&self, name: &str) -> Option<&SemanticZone> {
+        self.semantic_zones.get(name)
+    }
+
+    pub fn get_semantic_zones(&self) -> &HashMap<String, SemanticZone> {
+        &self.semantic_zones
+    }
+
+    pub fn clear_semantic_zones(&mut self) {
+        self.semantic_zones.clear();
+    }
+
+    /// # Returns
+    /// Every zone containing `position`, given in field meters, sorted by name.
+    pub fn get_semantic_zones_at(&self, position: Vector2<f32>) -> Vec<&SemanticZone> {
+        let mut zones: Vec<&SemanticZone> = self
+            .semantic_zones
+            .values()
+            .filter(|zone| zone.contains_point(position))
+            .collect();
+        zones.sort_by(|a, b| a.name.cmp(&b.name));
+
+        zones
+    }
+
+    /// # Returns
+    /// The zone closest to `position`, given in field meters, with its distance in meters (0 if
+    /// `position` is inside). Ties go to the first name.
+    pub fn get_nearest_semantic_zone(
+        &self,
+        position: Vector2<f32>,
+    ) -> Option<(&SemanticZone, f32)> {
+        self.semantic_zones
+            .values()
+            .map(|zone| (zone, zone.get_distance(position)))
+            .min_by(|(a, a_distance), (b, b_distance)| {
+                a_distance
+                    .total_cmp(b_distance)
+                    .then_with(|| a.name.cmp(&b.name))
+            })
+    }
+
     pub fn is_obstructed(&self, position: Vector2<i32>) -> bool {
         self.static_obstacles.contains(&position)
     }
 
-    pub fn add_hybrid_object(&mut self, object: &[f32; 2]) {
+    /// Integrates one sensor reading. Every cell the ray crosses before its end is observed free,
+    /// and the end cell is observed occupied if `is_hit`. Observed cells stop being unknown. Only
+    /// the part of the ray inside the grid is walked, and a ray ending outside it is only a miss.
+    /// # Returns
+    /// How many cells turned into observed obstacles or stopped being one.
+    /// # Arguments
+    /// * `sensor` - Where the sensor was, in field meters
+    /// * `end` - Where the ray ended, in field meters
+    /// * `is_hit` - Whether the ray hit something at `end`, false for readings at maximum range
+    pub fn integrate_observation(
+        &mut self,
+        sensor: Vector2<f32>,
+        end: Vector2<f32>,
+        is_hit: bool,
+    ) -> usize {
+        let (min, max) = self.get_cell_bounds();
+        let Some((clipped_sensor, clipped_end)) = shapes::clip_segment_to_box(
+            sensor,
+            end,
+            min.cast::<f32>() * self.square_size_meters,
+            (max + Vector2::new(1, 1)).cast::<f32>() * self.square_size_meters,
+        ) else {
+            return 0;
+        };
+        // Clipped ends lie on the grid's outer edge, which belongs to the next cell out
+        let to_cell = |position| self.meters_to_cell(position).sup(&min).inf(&max);
+        let cells = log_odds::get_ray_cells(to_cell(clipped_sensor), to_cell(clipped_end));
+        let Some((end_cell, passed)) = cells.split_last() else {
+            return 0;
+        };
+
+        let mut changed = Vec::new();
+        for cell in passed {
+            if self.observations.integrate_miss(*cell) {
+                changed.push(*cell);
+            }
+        }
+        let changed_end = if is_hit && !self.is_outside_grid(self.meters_to_cell(end)) {
+            self.observations.integrate_hit(*end_cell)
+        } else {
+            self.observations.integrate_miss(*end_cell)
+        };
+        if changed_end {
+            changed.push(*end_cell);
+        }
+
+        let observed: Vec<Vector2<i32>> = cells
+            .iter()
+            .copied()
+            .filter(|cell| self.unknown_cells.contains(cell))
+            .collect();
+        self.set_unknown_cells(&observed, false);
+
+        if let Some((min, max)) = get_position_bounds(&changed) {
+            self.mark_dirty(min, max);
+        }
+
+        changed.len()
+    }
+
+    pub fn is_observed_obstacle(&self, position: Vector2<i32>) -> bool {
+        self.observations.is_occupied(position)
+    }
+
+    pub fn get_observations(&self) -> &LogOddsGrid {
+        &self.observations
+    }
+
+    /// Forgets every observation and replaces the update rules with `config`.
+    pub fn reset_observations(&mut self, config: LogOddsConfig) {
+        if let Some((min, max)) = get_position_bounds(
+            &self
+                .observations
+                .get_occupied_cells()
+                .iter()
+                .copied()
+                .collect::<Vec<_>>(),
+        ) {
+            self.mark_dirty(min, max);
+        }
+
+        self.observations = LogOddsGrid::new(config);
+    }
+
+    /// Adds an anonymous point object that stays until the objects are cleared.
+    /// # Returns
+    /// The ID given to the object.
+    pub fn add_hybrid_object(&mut self, object: &[f32; 2]) -> u64 {
+        let mut id = self.next_hybrid_object_id;
+        // The counter stops at u64::MAX once that ID is used, after that free IDs are searched
+        while self.hybrid_objects.contains_key(&id) {
+            id = id.wrapping_add(1);
+        }
+        self.upsert_hybrid_object(id, HybridObject::new(Vector2::new(object[0], object[1])));
+
+        id
+    }
+
+    /// Adds the object with the given ID, or moves and updates it if it already exists.
+    /// # Returns
+    /// Whether the object is new.
+    pub fn upsert_hybrid_object(&mut self, id: u64, object: HybridObject) -> bool {
+        let is_new = self.remove_hybrid_object(id).is_none();
+
+        self.hybrid_obstacles
+            .add(&[object.position.x, object.position.y], id);
+        self.max_hybrid_object_radius = self.max_hybrid_object_radius.max(object.radius);
+        self.next_hybrid_object_id = self.next_hybrid_object_id.max(id.saturating_add(1));
+        self.hybrid_objects.insert(id, object);
+
+        is_new
+    }
+
+    /// # Returns
+    /// The removed object, if there was one with that ID.
+    pub fn remove_hybrid_object(&mut self, id: u64) -> Option<HybridObject> {
+        let object = self.hybrid_objects.remove(&id)?;
         self.hybrid_obstacles
-            .add(object, self.hybrid_obstacles.size());
+            .remove(&[object.position.x, object.position.y], id);
+
+        Some(object)
+    }
+
+    pub fn get_hybrid_object(&self, id: u64) -> Option<&HybridObject> {
+        self.hybrid_objects.get(&id)
+    }
+
+    pub fn get_hybrid_objects(&self) -> &HashMap<u64, HybridObject> {
+        &self.hybrid_objects
     }
 
     pub fn clear_hybrid_objects(&mut self) {
-        self.hybrid_obstacles = KdTree::new()
+        self.hybrid_obstacles = KdTree::new();
+        self.hybrid_objects.clear();
+        self.max_hybrid_object_radius = 0.0;
+    }
+
+    /// # Returns
+    /// Every hybrid object whose edge is at most `distance` cells from `position`, with its ID.
+    pub fn get_hybrid_objects_near(
+        &self,
+        position: Vector2<f32>,
+        distance: f32,
+    ) -> Vec<(u64, &HybridObject)> {
+        // Widened a hair so objects exactly on the boundary are found, the filter below is exact
+        let search_radius = distance + self.max_hybrid_object_radius;
+        self.hybrid_obstacles
+            .within_unsorted::<kiddo::SquaredEuclidean>(
+                &[position.x, position.y],
+                (search_radius * search_radius).next_up(),
+            )
+            .iter()
+            .filter_map(|neighbour| {
+                let object = self.hybrid_objects.get(&neighbour.item)?;
+                (object.get_edge_distance(position) <= distance).then_some((neighbour.item, object))
+            })
+            .collect()
+    }
+
+    /// Advances the match clock and forgets the hybrid objects and uncertainty fields that expired
+    /// by then. Uncertainty fields fade against this clock too.
+    /// # Returns
+    /// How many objects and fields expired.
+    pub fn set_time(&mut self, time: f64) -> usize {
+        self.time = time;
+
+        let expired_objects: Vec<u64> = self
+            .hybrid_objects
+            .iter()
+            .filter(|(_, object)| object.is_expired(time))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &expired_objects {
+            self.remove_hybrid_object(*id);
+        }
+
+        let expired_fields: Vec<u64> = self
+            .uncertenty_defs
+            .iter()
+            .filter(|(_, field)| field.is_expired(time))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &expired_fields {
+            self.remove_uncertenty_field(*id);
+        }
+
+        self.opponent_heatmap.prune(time);
+
+        expired_objects.len() + expired_fields.len()
+    }
+
+    pub fn get_time(&self) -> f64 {
+        self.time
+    }
+
+    /// Adds heat where every opponent robot among the hybrid objects is now. Called once per
+    /// tracker update, this builds up the lanes opponents use over the match.
+    /// # Returns
+    /// How many opponents were recorded.
+    /// # Arguments
+    /// * `amount` - The heat added at each opponent's center
+    pub fn record_opponent_heat(&mut self, amount: f32) -> usize {
+        let mut recorded = 0;
+        for object in self.hybrid_objects.values() {
+            if object.class == ObjectClass::OpponentRobot {
+                self.opponent_heatmap
+                    .add_heat(object.position, object.radius, amount, self.time);
+                recorded += 1;
+            }
+        }
+
+        recorded
+    }
+
+    /// Adds heat for an opponent seen by something other than the hybrid objects, now.
+    pub fn add_opponent_heat(&mut self, position: Vector2<f32>, radius: f32, amount: f32) {
+        self.opponent_heatmap
+            .add_heat(position, radius, amount, self.time);
+    }
+
+    /// # Returns
+    /// The opponent heat of `cell` now.
+    pub fn get_opponent_heat(&self, cell: Vector2<i32>) -> f32 {
+        self.opponent_heatmap.get_heat(cell, self.time)
+    }
+
+    pub fn get_opponent_heatmap(&self) -> &Heatmap {
+        &self.opponent_heatmap
+    }
+
+    /// Forgets all opponent heat and starts over with the given decay rate.
+    pub fn reset_opponent_heatmap(&mut self, decay_rate: f32) {
+        self.opponent_heatmap = Heatmap::new(decay_rate);
     }
 
     pub fn get_nearest(
@@ -169,63 +866,144 @@ impl HybridGrid {
         obstructions
     }
 
-    pub fn add_uncertenty_field(&mut self, center: Vector2<f32>, radius: f32, intensity: f32) {
-        let position = (self.uncertenty_defs.len() + 1) as u64;
-        if radius > self.max_field_radius {
-            self.max_field_radius = radius;
+    /// Adds a circular field with the default falloff that never fades, seen at the current time.
+    /// # Returns
+    /// The ID of the new field.
+    pub fn add_uncertenty_field(
+        &mut self,
+        center: Vector2<f32>,
+        radius: f32,
+        intensity: f32,
+    ) -> u64 {
+        let mut field = UncertentyField::new(center, FieldShape::Circle { radius }, intensity);
+        field.timestamp = self.time;
+
+        self.push_uncertenty_field(field)
+    }
+
+    /// # Returns
+    /// The ID of the new field.
+    pub fn push_uncertenty_field(&mut self, field: UncertentyField) -> u64 {
+        let id = self.next_uncertenty_field_id;
+        self.next_uncertenty_field_id += 1;
+        self.insert_uncertenty_field(id, field);
+
+        id
+    }
+
+    /// Moves, resizes or refreshes a field, keeping its ID.
+    /// # Returns
+    /// Whether there was a field with that ID.
+    pub fn update_uncertenty_field(&mut self, id: u64, field: UncertentyField) -> bool {
+        if self.remove_uncertenty_field(id).is_none() {
+            return false;
         }
 
+        self.insert_uncertenty_field(id, field);
+        true
+    }
+
+    fn insert_uncertenty_field(&mut self, id: u64, field: UncertentyField) {
+        self.max_field_radius = self.max_field_radius.max(field.shape.get_bounding_radius());
         self.uncertenty_fields
-            .add(&[center.x as f32, center.y as f32], position);
-        self.uncertenty_defs.insert(
-            position,
-            UncertentyField {
-                center,
-                radius,
-                intensity,
-            },
-        );
+            .add(&[field.center.x, field.center.y], id);
+        self.uncertenty_defs.insert(id, field);
+    }
+
+    /// # Returns
+    /// The removed field, if there was one with that ID.
+    pub fn remove_uncertenty_field(&mut self, id: u64) -> Option<UncertentyField> {
+        let field = self.uncertenty_defs.remove(&id)?;
+        self.uncertenty_fields
+            .remove(&[field.center.x, field.center.y], id);
+
+        Some(field)
+    }
+
+    pub fn get_uncertenty_def(&self, id: u64) -> Option<&UncertentyField> {
+        self.uncertenty_defs.get(&id)
     }
 
     pub fn clear_uncertenty_fields(&mut self) {
         self.uncertenty_defs.clear();
         self.uncertenty_fields = KdTree::new();
+        self.max_field_radius = 0.0;
     }
 
+    /// # Returns
+    /// The field covering `position` whose center is closest, with the distance to that center.
     pub fn get_uncertenty_field(&self, position: Vector2<f32>) -> Option<(UncertentyField, f32)> {
-        let output = self
-            .uncertenty_fields
-            .nearest_one::<kiddo::SquaredEuclidean>(&[position.x as f32, position.y as f32]);
-
-        if !self.uncertenty_defs.contains_key(&output.item) {
-            return None;
-        }
-
-        let field = self.uncertenty_defs.get(&output.item).unwrap();
+        self.get_uncertenty_fields(position)
+            .into_iter()
+            .map(|(field, _)| (field, (position - field.center).magnitude()))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(field, distance)| (field.clone(), distance))
+    }
 
-        if field.radius < output.distance {
-            return None;
-        }
+    /// # Returns
+    /// Every field covering `position`, each with the cost it adds there.
+    pub fn get_uncertenty_fields(&self, position: Vector2<f32>) -> Vec<(&UncertentyField, f32)> {
+        // No field reaches further than the largest radius, so nothing beyond it needs looking at
+        self.uncertenty_fields
+            .within_unsorted::<kiddo::SquaredEuclidean>(
+                &[position.x, position.y],
+                (self.max_field_radius * self.max_field_radius).next_up(),
+            )
+            .iter()
+            .filter_map(|neighbour| {
+                let field = self.uncertenty_defs.get(&neighbour.item)?;
+                Some((field, field.get_cost(position, self.time)?))
+            })
+            .collect()
+    }
 
-        Some((field.clone(), output.distance))
+    /// # Returns
+    /// The cost of all fields covering `position` combined, or `None` if no field covers it.
+    pub fn get_uncertenty_cost(
+        &self,
+        position: Vector2<f32>,
+        combine: FieldCombine,
+    ) -> Option<f32> {
+        self.get_uncertenty_fields(position)
+            .into_iter()
+            .map(|(_, cost)| cost)
+            .reduce(|a, b| match combine {
+                FieldCombine::Sum => a + b,
+                FieldCombine::Max => a.max(b),
+            })
     }
 
+    /// # Returns
+    /// The cost of a circular field `distance_field` cells in radius at `distance_cur` cells from
+    /// its center, under the default ease out falloff.
+    #[deprecated(note = "use `Falloff::apply` or `UncertentyField::get_cost` instead")]
     pub fn uncertenty_field_cost_ramping(
         &self,
         distance_cur: f32,
         distance_field: f32,
         intensity: f32,
     ) -> f32 {
-        let distance_ratio = distance_cur / distance_field;
-        let eased_ratio = 1.0 - (1.0 - distance_ratio).powi(2); // Quadratic easing out
-        eased_ratio * intensity
+        Falloff::EaseOut.apply(distance_cur / distance_field) * intensity
     }
 }
 
+fn get_position_bounds(positions: &[Vector2<i32>]) -> Option<(Vector2<i32>, Vector2<i32>)> {
+    let first = *positions.first()?;
+    Some(
+        positions
+            .iter()
+            .fold((first, first), |(min, max), position| {
+                (min.inf(position), max.sup(position))
+            }),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use hybrid_objects::ObjectClass;
     use nalgebra::Vector2;
+    use uncertainty_fields::Falloff;
 
     #[test]
     fn test_grid_creation() {
@@ -267,6 +1045,55 @@ mod tests {
         assert!(nearest_after_clear.is_empty());
     }
 
+    #[test]
+    fn test_hybrid_object_lifecycle() {
+        let mut grid = HybridGrid::new_raw(100, 100, 1.0, 0, 0);
+        let robot = HybridObject {
+            position: Vector2::new(5.0, 5.0),
+            radius: 2.0,
+            class: ObjectClass::Robot,
+            timestamp: 1.0,
+            time_to_live: Some(0.5),
+        };
+
+        assert!(grid.upsert_hybrid_object(7, robot.clone()));
+        assert_eq!(
+            grid.get_hybrid_objects_near(Vector2::new(7.5, 5.0), 1.0)
+                .len(),
+            1
+        );
+
+        // Seen again somewhere else
+        let moved = HybridObject {
+            position: Vector2::new(20.0, 5.0),
+            timestamp: 1.2,
+            ..robot
+        };
+        assert!(!grid.upsert_hybrid_object(7, moved));
+        assert!(grid
+            .get_hybrid_objects_near(Vector2::new(7.5, 5.0), 1.0)
+            .is_empty());
+        assert_eq!(grid.get_nearest(Vector2::new(20, 5), 1.0).len(), 1);
+
+        // Anonymous objects never reuse an ID
+        assert_eq!(grid.add_hybrid_object(&[50.0, 50.0]), 8);
+
+        assert_eq!(grid.set_time(1.5), 0);
+        assert_eq!(grid.set_time(1.7), 1);
+        assert!(grid.get_hybrid_object(7).is_none());
+        assert!(grid.get_nearest(Vector2::new(20, 5), 1.0).is_empty());
+        assert!(grid.get_hybrid_object(8).is_some());
+
+        assert!(grid.remove_hybrid_object(8).is_some());
+        assert!(grid.get_hybrid_objects().is_empty());
+
+        // Java passes -1 as u64::MAX, which must not wrap the counter onto existing IDs
+        grid.upsert_hybrid_object(u64::MAX, HybridObject::new(Vector2::new(1.0, 1.0)));
+        let id = grid.add_hybrid_object(&[2.0, 2.0]);
+        assert_ne!(id, u64::MAX);
+        assert_eq!(grid.get_hybrid_objects().len(), 2);
+    }
+
     #[test]
     fn test_obstruction_in_radius() {
         let mut grid = HybridGrid::new_raw(100, 100, 1.0, 0, 0);
@@ -286,10 +1113,277 @@ mod tests {
 
         assert!(field.is_some());
         let (field, distance) = field.unwrap();
-        assert_eq!(field.radius, 2.0);
+        assert_eq!(field.shape, FieldShape::Circle { radius: 2.0 });
         assert_eq!(field.intensity, 0.5);
         assert_eq!(distance, 0.0);
         grid.clear_uncertenty_fields();
         assert!(grid.get_uncertenty_field(center).is_none());
     }
+
+    #[test]
+    fn test_overlapping_uncertainty_fields() {
+        let mut grid = HybridGrid::new_raw(100, 100, 1.0, 0, 0);
+        grid.add_uncertenty_field(Vector2::new(0.0, 0.0), 10.0, 1.0);
+        grid.add_uncertenty_field(Vector2::new(6.0, 0.0), 1.0, 1.0);
+
+        // Closer to the small field's center, but only inside the large field
+        let position = Vector2::new(4.5, 0.0);
+        let (field, distance) = grid.get_uncertenty_field(position).unwrap();
+        assert_eq!(field.shape, FieldShape::Circle { radius: 10.0 });
+        assert_eq!(distance, 4.5);
+
+        let position = Vector2::new(5.5, 0.0);
+        let fields = grid.get_uncertenty_fields(position);
+        assert_eq!(fields.len(), 2);
+        let large = Falloff::default().apply(0.55);
+        let small = Falloff::default().apply(0.5);
+        let sum = grid
+            .get_uncertenty_cost(position, FieldCombine::Sum)
+            .unwrap();
+        let max = grid
+            .get_uncertenty_cost(position, FieldCombine::Max)
+            .unwrap();
+        assert!((sum - (large + small)).abs() < 1e-6);
+        assert!((max - large.max(small)).abs() < 1e-6);
+        #[allow(deprecated)]
+        let ramped = grid.uncertenty_field_cost_ramping(5.5, 10.0, 1.0);
+        assert_eq!(ramped, large);
+
+        assert!(grid
+            .get_uncertenty_cost(Vector2::new(11.0, 0.0), FieldCombine::Sum)
+            .is_none());
+    }
+
+    #[test]
+    fn test_uncertainty_field_lifecycle() {
+        let mut grid = HybridGrid::new_raw(100, 100, 1.0, 0, 0);
+        let first = grid.add_uncertenty_field(Vector2::new(0.0, 0.0), 2.0, 1.0);
+        let second = grid.add_uncertenty_field(Vector2::new(10.0, 0.0), 2.0, 1.0);
+        assert!(grid.remove_uncertenty_field(first).is_some());
+
+        // IDs stay unique after a removal
+        let third = grid.add_uncertenty_field(Vector2::new(20.0, 0.0), 2.0, 1.0);
+        assert_ne!(third, second);
+
+        let mut moved = grid.get_uncertenty_def(second).unwrap().clone();
+        moved.center = Vector2::new(30.0, 0.0);
+        moved.shape = moved.shape.scaled(2.0);
+        moved.decay_rate = 1.0;
+        moved.time_to_live = Some(5.0);
+        assert!(grid.update_uncertenty_field(second, moved));
+        assert!(grid.get_uncertenty_field(Vector2::new(10.0, 0.0)).is_none());
+        assert!(grid.get_uncertenty_field(Vector2::new(33.0, 0.0)).is_some());
+
+        // The default falloff reaches the full intensity at the edge
+        grid.set_time(1.0);
+        let faded = grid
+            .get_uncertenty_cost(Vector2::new(34.0, 0.0), FieldCombine::Sum)
+            .unwrap();
+        assert!((faded - (-1.0f32).exp()).abs() < 1e-6);
+
+        assert_eq!(grid.set_time(5.0), 1);
+        assert!(grid.get_uncertenty_def(second).is_none());
+        assert!(grid.get_uncertenty_def(third).is_some());
+        assert!(!grid.update_uncertenty_field(
+            first,
+            UncertentyField::new(
+                Vector2::new(0.0, 0.0),
+                FieldShape::Circle { radius: 1.0 },
+                1.0,
+            )
+        ));
+    }
+
+    #[test]
+    fn test_opponent_heat() {
+        let mut grid = HybridGrid::new_raw(100, 100, 1.0, 0, 0);
+        let mut opponent = HybridObject::new(Vector2::new(10.0, 10.0));
+        opponent.class = ObjectClass::OpponentRobot;
+        opponent.radius = 2.0;
+        grid.upsert_hybrid_object(0, opponent);
+        grid.add_hybrid_object(&[30.0, 30.0]);
+
+        assert_eq!(grid.record_opponent_heat(1.0), 1);
+        grid.remove_hybrid_object(0);
+        assert_eq!(grid.get_opponent_heat(Vector2::new(10, 10)), 1.0);
+        assert_eq!(grid.get_opponent_heat(Vector2::new(30, 30)), 0.0);
+
+        // Still remembered after the opponent left, but fading
+        grid.set_time(10.0);
+        let heat = grid.get_opponent_heat(Vector2::new(10, 10));
+        assert!(heat > 0.0 && heat < 1.0);
+    }
+
+    #[test]
+    fn test_static_obstacle_edits() {
+        let mut grid = HybridGrid::new_raw(100, 100, 1.0, 0, 0);
+
+        grid.push_static_obstacle(Vector2::new(1, 1));
+        assert!(grid.remove_static_obstacle(Vector2::new(1, 1)));
+        assert!(!grid.remove_static_obstacle(Vector2::new(1, 1)));
+        assert!(!grid.is_obstructed(Vector2::new(1, 1)));
+
+        let wall: Vec<Vector2<i32>> = (0..5).map(|i| Vector2::new(i, 3)).collect();
+        assert_eq!(grid.set_static_obstacles(&wall, true), 5);
+        assert_eq!(grid.set_static_obstacles(&wall, true), 0);
+        assert!(grid.is_obstructed(Vector2::new(4, 3)));
+
+        assert_eq!(
+            grid.clear_static_region(Vector2::new(1, 0), Vector2::new(2, 5)),
+            2
+        );
+        assert!(grid.is_obstructed(Vector2::new(0, 3)));
+        assert!(!grid.is_obstructed(Vector2::new(2, 3)));
+    }
+
+    #[test]
+    fn test_dirty_regions() {
+        let mut grid = HybridGrid::new(100, 100, 1.0, vec![Vector2::new(0, 0)], 0, 0);
+        assert_eq!(grid.get_version(), 0);
+
+        grid.push_static_obstacle(Vector2::new(1, 1));
+        let version = grid.get_version();
+        grid.set_static_obstacles(&[Vector2::new(2, 5), Vector2::new(4, 3)], true);
+        grid.push_static_obstacle(Vector2::new(1, 1));
+
+        let dirty = grid.get_dirty_regions_since(version).unwrap();
+        assert_eq!(grid.get_version(), version + 1);
+        assert_eq!(
+            dirty,
+            vec![DirtyRegion {
+                min: Vector2::new(2, 3),
+                max: Vector2::new(4, 5),
+                version: version + 1,
+            }]
+        );
+        assert_eq!(grid.get_dirty_regions_since(0).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_dirty_regions_overflow() {
+        let mut grid = HybridGrid::new_raw(1000, 1000, 1.0, 0, 0);
+        for i in 0..(MAX_DIRTY_REGIONS as i32 + 10) {
+            grid.push_static_obstacle(Vector2::new(i, 0));
+        }
+
+        assert!(grid.get_dirty_regions_since(0).is_none());
+        let recent = grid
+            .get_dirty_regions_since(grid.get_version() - 5)
+            .unwrap();
+        assert_eq!(recent.len(), 5);
+    }
+
+    #[test]
+    fn test_meters_to_cell() {
+        let grid = HybridGrid::new(100, 100, 0.5, vec![], 50, 50);
+
+        assert_eq!(
+            grid.meters_to_cell(Vector2::new(0.0, 0.0)),
+            Vector2::new(0, 0)
+        );
+        assert_eq!(
+            grid.meters_to_cell(Vector2::new(1.2, 0.49)),
+            Vector2::new(2, 0)
+        );
+        assert_eq!(
+            grid.meters_to_cell(Vector2::new(-0.1, 0.5)),
+            Vector2::new(-1, 1)
+        );
+        assert_eq!(
+            grid.cell_to_meters(Vector2::new(2, 0)),
+            Vector2::new(1.25, 0.25)
+        );
+        assert_eq!(
+            grid.get_cell_bounds(),
+            (Vector2::new(0, 0), Vector2::new(99, 99))
+        );
+    }
+
+    #[test]
+    fn test_cell_costs() {
+        let mut grid = HybridGrid::new(100, 100, 1.0, vec![], 50, 50);
+        let cells = [Vector2::new(1, 1), Vector2::new(2, 3)];
+
+        assert_eq!(grid.set_cell_costs(&cells, 2.0), 2);
+        assert_eq!(grid.get_cell_cost(Vector2::new(2, 3)), 2.0);
+        assert_eq!(grid.get_cell_cost(Vector2::new(0, 0)), 0.0);
+        assert_eq!(grid.set_cell_costs(&cells, 2.0), 0);
+        assert_eq!(grid.get_version(), 1);
+
+        grid.set_cell_cost(Vector2::new(1, 1), 0.0);
+        assert!(!grid.get_cell_costs().contains_key(&Vector2::new(1, 1)));
+
+        grid.clear_cell_costs();
+        assert!(grid.get_cell_costs().is_empty());
+        assert_eq!(grid.get_version(), 3);
+    }
+
+    #[test]
+    fn test_unknown_cells() {
+        let mut grid = HybridGrid::new(100, 100, 1.0, vec![Vector2::new(1, 1)], 50, 50);
+        let path = [Vector2::new(0, 0), Vector2::new(1, 1), Vector2::new(2, 2)];
+
+        assert_eq!(grid.set_unknown_cells(&path[1..], true), 2);
+        assert_eq!(grid.get_cell_state(Vector2::new(1, 1)), CellState::Unknown);
+        assert_eq!(grid.get_cell_state(Vector2::new(0, 0)), CellState::Free);
+        assert!(!grid.is_obstructed(Vector2::new(1, 1)));
+        assert_eq!(grid.get_unknown_share(&path), 1.0);
+
+        grid.push_static_obstacle(Vector2::new(2, 2));
+        assert_eq!(grid.get_cell_state(Vector2::new(2, 2)), CellState::Occupied);
+        assert_eq!(grid.get_unknown_share(&path), 0.5);
+
+        assert_eq!(grid.set_unknown_cells(&path, false), 1);
+        assert!(grid.get_unknown_cells().is_empty());
+    }
+
+    #[test]
+    fn test_observations() {
+        let mut grid = HybridGrid::new(20, 20, 0.5, vec![Vector2::new(4, 1)], 10, 10);
+        grid.set_unknown_cells(&[Vector2::new(2, 1)], true);
+        let sensor = Vector2::new(0.25, 0.75);
+        let target = Vector2::new(3.25, 0.75);
+
+        for _ in 0..2 {
+            grid.integrate_observation(sensor, target, true);
+        }
+        assert!(grid.is_observed_obstacle(Vector2::new(6, 1)));
+        assert!(!grid.is_unknown(Vector2::new(2, 1)));
+        // Clearing rays don't touch the static map
+        assert!(grid.is_obstructed(Vector2::new(4, 1)));
+
+        let version = grid.get_version();
+        for _ in 0..10 {
+            grid.integrate_observation(sensor, Vector2::new(5.25, 0.75), false);
+        }
+        assert!(!grid.is_observed_obstacle(Vector2::new(6, 1)));
+        assert!(grid.get_dirty_regions_since(version).unwrap().len() == 1);
+    }
+
+    #[test]
+    fn test_observation_far_outside_grid() {
+        // Cells 0..=19 on both axes, 10 m across
+        let mut grid = HybridGrid::new(20, 20, 0.5, vec![], 10, 10);
+        grid.set_unknown_cells(&[Vector2::new(19, 1), Vector2::new(5, 5)], true);
+
+        // Only the part inside the grid is walked, and its last cell isn't where the ray hit
+        for _ in 0..3 {
+            grid.integrate_observation(Vector2::new(0.25, 0.75), Vector2::new(1e9, 0.75), true);
+        }
+        assert!(!grid.is_unknown(Vector2::new(19, 1)));
+        assert!(!grid.is_observed_obstacle(Vector2::new(19, 1)));
+
+        // A sensor outside the grid still clears what its ray crosses inside
+        grid.integrate_observation(
+            Vector2::new(-100.0, -100.0),
+            Vector2::new(2.75, 2.75),
+            false,
+        );
+        assert!(!grid.is_unknown(Vector2::new(5, 5)));
+
+        assert_eq!(
+            grid.integrate_observation(Vector2::new(-5.0, 0.0), Vector2::new(-1.0, 20.0), true),
+            0
+        );
+    }
 }
@@ -0,0 +1,319 @@
+use std::f32::consts::PI;
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::time_structures::TimedPath;
+
+use super::{
+    uncertainty_fields::{FieldShape, UncertentyField},
+    zones::{ApproachPose, SemanticZone},
+    HybridGrid,
+};
+
+/// How the field looks from the other alliance. Following `orientation.txt`, forward (+y) points
+/// from one alliance wall towards the other, so both symmetries swap the ends of the y axis.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FieldSymmetry {
+    /// Mirrored across the center line between the alliance walls, so left stays left
+    #[default]
+    Mirror,
+    /// Turned 180° around the field center, so left becomes right as well
+    Rotational,
+}
+
+impl FieldSymmetry {
+    /// # Returns
+    /// The symmetry with the given index in declaration order, `Mirror` for anything else.
+    pub fn from_index(index: i32) -> Self {
+        match index {
+            1 => Self::Rotational,
+            _ => Self::Mirror,
+        }
+    }
+}
+
+/// Moves things between the two alliance frames of a grid. Flipping twice gives back the original,
+/// so the same transform goes both ways.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AllianceTransform {
+    symmetry: FieldSymmetry,
+    /// The first plus the last cell inside the grid, which every flipped coordinate is taken from
+    cell_sum: Vector2<i32>,
+    square_size_meters: f32,
+}
+
+impl AllianceTransform {
+    /// A transform about the center of `grid`, which should cover the whole field.
+    pub fn new(grid: &HybridGrid, symmetry: FieldSymmetry) -> Self {
+        let (min, max) = grid.get_cell_bounds();
+
+        Self {
+            symmetry,
+            cell_sum: min + max,
+            square_size_meters: grid.get_square_size_meters(),
+        }
+    }
+
+    pub fn get_symmetry(&self) -> FieldSymmetry {
+        self.symmetry
+    }
+
+    pub fn flip_cell(&self, cell: Vector2<i32>) -> Vector2<i32> {
+        match self.symmetry {
+            FieldSymmetry::Mirror => Vector2::new(cell.x, self.cell_sum.y - cell.y),
+            FieldSymmetry::Rotational => self.cell_sum - cell,
+        }
+    }
+
+    /// # Arguments
+    /// * `point` - A position in grid cells, where the center of cell i is at i
+    pub fn flip_point(&self, point: Vector2<f32>) -> Vector2<f32> {
+        let sum = self.cell_sum.cast::<f32>();
+        match self.symmetry {
+            FieldSymmetry::Mirror => Vector2::new(point.x, sum.y - point.y),
+            FieldSymmetry::Rotational => sum - point,
+        }
+    }
+
+    /// # Arguments
+    /// * `point` - A position in field meters
+    pub fn flip_meters(&self, point: Vector2<f32>) -> Vector2<f32> {
+        // Cell centers sit half a cell above i * square size
+        let sum = (self.cell_sum.cast::<f32>() + Vector2::new(1.0, 1.0)) * self.square_size_meters;
+        match self.symmetry {
+            FieldSymmetry::Mirror => Vector2::new(point.x, sum.y - point.y),
+            FieldSymmetry::Rotational => sum - point,
+        }
+    }
+
+    /// Flips a direction or velocity, which has no position to flip around.
+    pub fn flip_direction(&self, direction: Vector2<f32>) -> Vector2<f32> {
+        match self.symmetry {
+            FieldSymmetry::Mirror => Vector2::new(direction.x, -direction.y),
+            FieldSymmetry::Rotational => -direction,
+        }
+    }
+
+    /// Flips a heading, in radians counterclockwise from +x.
+    pub fn flip_rotation(&self, rotation: f32) -> f32 {
+        match self.symmetry {
+            FieldSymmetry::Mirror => -rotation,
+            FieldSymmetry::Rotational => rotation + PI,
+        }
+    }
+
+    pub fn flip_path(&self, path: &[Vector2<i32>]) -> Vec<Vector2<i32>> {
+        path.iter().map(|cell| self.flip_cell(*cell)).collect()
+    }
+
+    /// Flips (x, y, time) waypoints in grid cells, keeping their times.
+    pub fn flip_timed_path(&self, path: &[Vector3<f64>]) -> Vec<Vector3<f64>> {
+        path.iter()
+            .map(|waypoint| {
+                let flipped = self.flip_point(waypoint.xy().cast::<f32>()).cast::<f64>();
+                Vector3::new(flipped.x, flipped.y, waypoint.z)
+            })
+            .collect()
+    }
+
+    /// Flips a trajectory whose positions are in grid cells, keeping its timing.
+    pub fn flip_trajectory(&self, trajectory: &TimedPath) -> TimedPath {
+        trajectory.transformed(
+            |position| self.flip_point(position.cast::<f32>()).cast::<f64>(),
+            |direction| self.flip_direction(direction.cast::<f32>()).cast::<f64>(),
+        )
+    }
+
+    pub fn flip_field(&self, field: &UncertentyField) -> UncertentyField {
+        let shape = match (&field.shape, self.symmetry) {
+            (FieldShape::Ellipse { radii, rotation }, _) => FieldShape::Ellipse {
+                radii: *radii,
+                rotation: self.flip_rotation(*rotation),
+            },
+            (FieldShape::Rectangle { size, rotation }, _) => FieldShape::Rectangle {
+                size: *size,
+                rotation: self.flip_rotation(*rotation),
+            },
+            // A half turn leaves a covariance as it is, a mirror reverses how x and y correlate
+            (FieldShape::Gaussian { covariance }, FieldSymmetry::Mirror) => {
+                let mut covariance = *covariance;
+                covariance[(0, 1)] = -covariance[(0, 1)];
+                covariance[(1, 0)] = -covariance[(1, 0)];
+                FieldShape::Gaussian { covariance }
+            }
+            (shape, _) => shape.clone(),
+        };
+
+        UncertentyField {
+            center: self.flip_point(field.center),
+            shape,
+            ..field.clone()
+        }
+    }
+
+    /// Flips a zone's outline and approach poses, keeping its name.
+    pub fn flip_zone(&self, zone: &SemanticZone) -> SemanticZone {
+        SemanticZone {
+            name: zone.name.clone(),
+            polygon: zone.polygon.iter().map(|c| self.flip_meters(*c)).collect(),
+            approach_poses: zone
+                .approach_poses
+                .iter()
+                .map(|pose| {
+                    ApproachPose::new(
+                        self.flip_meters(pose.position),
+                        self.flip_rotation(pose.heading),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// # Returns
+    /// A copy of `grid` seen from the other alliance, with its obstacles, costs, observations,
+    /// keep-out zones, named zones, objects, fields and heat flipped. IDs stay the same, so sources
+    /// tracked by ID can keep updating them, as long as they flip what they send too.
+    pub fn flip_grid(&self, grid: &HybridGrid) -> HybridGrid {
+        let mut flipped = grid.clone();
+        let flip = |cell: Vector2<i32>| self.flip_cell(cell);
+
+        flipped.static_obstacles = grid.static_obstacles.iter().map(|c| flip(*c)).collect();
+        flipped.unknown_cells = grid.unknown_cells.iter().map(|c| flip(*c)).collect();
+        flipped.cell_costs = grid
+            .cell_costs
+            .iter()
+            .map(|(cell, cost)| (flip(*cell), *cost))
+            .collect();
+        flipped.observations = grid.observations.map_cells(flip);
+        flipped.opponent_heatmap = grid.opponent_heatmap.map_cells(flip);
+        for zone in flipped.keep_out_zones.values_mut() {
+            zone.cells = zone.cells.iter().map(|c| flip(*c)).collect();
+        }
+        for zone in flipped.semantic_zones.values_mut() {
+            *zone = self.flip_zone(zone);
+        }
+
+        flipped.clear_hybrid_objects();
+        for (id, object) in &grid.hybrid_objects {
+            let mut object = object.clone();
+            object.position = self.flip_point(object.position);
+            flipped.upsert_hybrid_object(*id, object);
+        }
+
+        flipped.clear_uncertenty_fields();
+        for (id, field) in &grid.uncertenty_defs {
+            flipped.insert_uncertenty_field(*id, self.flip_field(field));
+        }
+
+        let (min, max) = grid.get_cell_bounds();
+        flipped.mark_dirty(min, max);
+
+        flipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Matrix2;
+
+    use super::*;
+    use crate::hybrid_grid::hybrid_objects::HybridObject;
+
+    #[test]
+    fn test_flip_cells() {
+        // Cells 0..=9 on x and 0..=19 on y
+        let grid = HybridGrid::new_raw(10, 20, 0.1, 5, 10);
+
+        let mirror = AllianceTransform::new(&grid, FieldSymmetry::Mirror);
+        assert_eq!(mirror.flip_cell(Vector2::new(2, 3)), Vector2::new(2, 16));
+        assert_eq!(mirror.flip_rotation(0.5), -0.5);
+        assert!(
+            (mirror.flip_meters(Vector2::new(0.25, 0.35)) - Vector2::new(0.25, 1.65)).magnitude()
+                < 1e-5
+        );
+
+        let rotational = AllianceTransform::new(&grid, FieldSymmetry::Rotational);
+        assert_eq!(
+            rotational.flip_cell(Vector2::new(2, 3)),
+            Vector2::new(7, 16)
+        );
+        assert_eq!(
+            rotational.flip_point(Vector2::new(0.0, 0.5)),
+            Vector2::new(9.0, 18.5)
+        );
+        assert_eq!(
+            rotational.flip_direction(Vector2::new(1.0, 2.0)),
+            Vector2::new(-1.0, -2.0)
+        );
+    }
+
+    #[test]
+    fn test_flip_grid() {
+        let mut grid = HybridGrid::new_raw(10, 20, 0.1, 5, 10);
+        grid.push_static_obstacle(Vector2::new(1, 2));
+        grid.set_cell_cost(Vector2::new(3, 4), 2.0);
+        let mut object = HybridObject::new(Vector2::new(4.0, 1.0));
+        object.radius = 1.0;
+        grid.upsert_hybrid_object(7, object);
+        grid.set_semantic_zone(
+            SemanticZone::new(
+                "station",
+                vec![
+                    Vector2::new(0.0, 0.0),
+                    Vector2::new(0.4, 0.0),
+                    Vector2::new(0.4, 0.4),
+                ],
+            )
+            .with_approach_poses(vec![ApproachPose::new(Vector2::new(0.5, 0.5), 0.5)]),
+        );
+        let field_id = grid.push_uncertenty_field(UncertentyField::new(
+            Vector2::new(2.0, 2.0),
+            FieldShape::Gaussian {
+                covariance: Matrix2::new(4.0, 1.0, 1.0, 2.0),
+            },
+            1.0,
+        ));
+        let version = grid.get_version();
+
+        let transform = AllianceTransform::new(&grid, FieldSymmetry::Mirror);
+        let flipped = transform.flip_grid(&grid);
+
+        assert!(flipped.is_obstructed(Vector2::new(1, 17)));
+        assert!(!flipped.is_obstructed(Vector2::new(1, 2)));
+        assert_eq!(flipped.get_cell_cost(Vector2::new(3, 15)), 2.0);
+        assert_eq!(
+            flipped.get_hybrid_object(7).unwrap().position,
+            Vector2::new(4.0, 18.0)
+        );
+        assert_eq!(
+            flipped
+                .get_hybrid_objects_near(Vector2::new(4.0, 18.0), 0.5)
+                .len(),
+            1
+        );
+
+        let field = flipped.get_uncertenty_def(field_id).unwrap();
+        assert_eq!(field.center, Vector2::new(2.0, 17.0));
+        assert_eq!(
+            field.shape,
+            FieldShape::Gaussian {
+                covariance: Matrix2::new(4.0, -1.0, -1.0, 2.0)
+            }
+        );
+        assert!(flipped.get_version() > version);
+
+        let zone = flipped.get_semantic_zone("station").unwrap();
+        assert!(zone.contains_point(Vector2::new(0.3, 1.9)));
+        assert!(!zone.contains_point(Vector2::new(0.3, 0.1)));
+        assert!((zone.approach_poses[0].position - Vector2::new(0.5, 1.5)).magnitude() < 1e-5);
+        assert_eq!(zone.approach_poses[0].heading, -0.5);
+
+        let restored = transform.flip_grid(&flipped);
+        assert_eq!(restored.get_static_obstacles(), grid.get_static_obstacles());
+        assert_eq!(restored.get_cell_costs(), grid.get_cell_costs());
+        assert_eq!(
+            restored.get_uncertenty_def(field_id),
+            grid.get_uncertenty_def(field_id)
+        );
+    }
+}
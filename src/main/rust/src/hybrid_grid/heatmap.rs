@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use nalgebra::Vector2;
+
+/// Heat below this is forgotten when the heatmap is pruned.
+pub const MIN_HEAT: f32 = 1e-3;
+
+/// Accumulated presence over time, fading exponentially. Every cell remembers its heat at the
+/// last time it was touched and is decayed lazily when read, so time passing costs nothing.
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+    /// The fraction of heat lost per second, exponentially
+    decay_rate: f32,
+    heat: HashMap<Vector2<i32>, (f32, f64)>,
+}
+
+impl Default for Heatmap {
+    /// Halves about every 35 seconds, so a lane used early in a match still shows in endgame.
+    fn default() -> Self {
+        Self::new(0.02)
+    }
+}
+
+impl Heatmap {
+    pub fn new(decay_rate: f32) -> Self {
+        Self {
+            decay_rate,
+            heat: HashMap::new(),
+        }
+    }
+
+    pub fn get_decay_rate(&self) -> f32 {
+        self.decay_rate
+    }
+
+    /// Spreads `amount` of heat over a disc, fading linearly from the center to the edge.
+    /// # Arguments
+    /// * `center` - The center of the disc, in grid cells
+    /// * `radius` - The radius of the disc, in grid cells
+    /// * `amount` - The heat added at the center
+    /// * `time` - The match time of the observation
+    pub fn add_heat(&mut self, center: Vector2<f32>, radius: f32, amount: f32, time: f64) {
+        let reach = radius.max(0.5);
+        let min = Vector2::new((center.x - reach).ceil(), (center.y - reach).ceil());
+        let max = Vector2::new((center.x + reach).floor(), (center.y + reach).floor());
+
+        for x in min.x as i32..=max.x as i32 {
+            for y in min.y as i32..=max.y as i32 {
+                let cell = Vector2::new(x, y);
+                let distance = (Vector2::new(x as f32, y as f32) - center).magnitude();
+                if distance > reach {
+                    continue;
+                }
+
+                let heat = self.get_heat(cell, time) + amount * (1.0 - distance / reach);
+                self.heat.insert(cell, (heat, time));
+            }
+        }
+    }
+
+    /// # Returns
+    /// The heat of `cell` at `time`.
+    pub fn get_heat(&self, cell: Vector2<i32>, time: f64) -> f32 {
+        self.heat.get(&cell).map_or(0.0, |(heat, updated)| {
+            let elapsed = (time - updated).max(0.0) as f32;
+            heat * (-self.decay_rate * elapsed).exp()
+        })
+    }
+
+    /// Forgets the cells that have cooled below `MIN_HEAT` by `time`.
+    pub fn prune(&mut self, time: f64) {
+        let decay_rate = self.decay_rate;
+        self.heat.retain(|_, (heat, updated)| {
+            let elapsed = (time - *updated).max(0.0) as f32;
+            *heat * (-decay_rate * elapsed).exp() >= MIN_HEAT
+        });
+    }
+
+    /// # Returns
+    /// The same heat with every cell moved by `map`, which must not send two cells to one.
+    pub fn map_cells(&self, map: impl Fn(Vector2<i32>) -> Vector2<i32>) -> Self {
+        Self {
+            decay_rate: self.decay_rate,
+            heat: self
+                .heat
+                .iter()
+                .map(|(cell, heat)| (map(*cell), *heat))
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heat.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heat.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.heat.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heat_accumulates_and_decays() {
+        let mut heatmap = Heatmap::new(0.5);
+        let cell = Vector2::new(3, 3);
+
+        heatmap.add_heat(Vector2::new(3.0, 3.0), 2.0, 1.0, 0.0);
+        heatmap.add_heat(Vector2::new(3.0, 3.0), 2.0, 1.0, 0.0);
+        assert_eq!(heatmap.get_heat(cell, 0.0), 2.0);
+        assert_eq!(heatmap.get_heat(Vector2::new(4, 3), 0.0), 1.0);
+        assert_eq!(heatmap.get_heat(Vector2::new(6, 3), 0.0), 0.0);
+
+        assert!((heatmap.get_heat(cell, 2.0) - 2.0 * (-1.0f32).exp()).abs() < 1e-6);
+
+        heatmap.prune(1.0);
+        assert!(!heatmap.is_empty());
+        heatmap.prune(30.0);
+        assert!(heatmap.is_empty());
+    }
+}
@@ -0,0 +1,67 @@
+use nalgebra::Vector2;
+
+/// What a hybrid object is, so planners can treat them differently.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ObjectClass {
+    /// A robot of either alliance
+    Robot,
+    GamePiece,
+    Human,
+    PartnerRobot,
+    OpponentRobot,
+    #[default]
+    Unknown,
+}
+
+impl ObjectClass {
+    /// # Returns
+    /// The class with the given index in declaration order, `Unknown` for anything else.
+    pub fn from_index(index: i32) -> Self {
+        match index {
+            0 => Self::Robot,
+            1 => Self::GamePiece,
+            2 => Self::Human,
+            3 => Self::PartnerRobot,
+            4 => Self::OpponentRobot,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// A short-lived obstacle such as a detected robot or game piece. Positions and radii are in grid
+/// cells, like every other hybrid object query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HybridObject {
+    pub position: Vector2<f32>,
+    pub radius: f32,
+    pub class: ObjectClass,
+    /// When the object was last seen, in match time seconds
+    pub timestamp: f64,
+    /// How long after `timestamp` the object is forgotten, `None` to keep it until removed
+    pub time_to_live: Option<f64>,
+}
+
+impl HybridObject {
+    /// An object of unknown class without size or expiry, like the ones `add_hybrid_object`
+    /// creates.
+    pub fn new(position: Vector2<f32>) -> Self {
+        Self {
+            position,
+            radius: 0.0,
+            class: ObjectClass::Unknown,
+            timestamp: 0.0,
+            time_to_live: None,
+        }
+    }
+
+    pub fn is_expired(&self, time: f64) -> bool {
+        self.time_to_live
+            .is_some_and(|time_to_live| self.timestamp + time_to_live <= time)
+    }
+
+    /// # Returns
+    /// The distance from `position` to the edge of the object, 0 inside it.
+    pub fn get_edge_distance(&self, position: Vector2<f32>) -> f32 {
+        ((position - self.position).magnitude() - self.radius).max(0.0)
+    }
+}
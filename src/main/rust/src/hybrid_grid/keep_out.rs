@@ -0,0 +1,71 @@
+use std::collections::HashSet;
+
+use nalgebra::Vector2;
+
+/// A stretch of match time, from `start` (inclusive) to `end` (exclusive), in seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeWindow {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl TimeWindow {
+    /// A window that is always active.
+    pub const ALWAYS: Self = Self {
+        start: f64::NEG_INFINITY,
+        end: f64::INFINITY,
+    };
+
+    pub fn new(start: f64, end: f64) -> Self {
+        Self { start, end }
+    }
+
+    pub fn contains(&self, time: f64) -> bool {
+        self.start <= time && time < self.end
+    }
+}
+
+/// Cells that may not be entered while any of the windows is active, e.g. a protected zone
+/// during auto.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeepOutZone {
+    pub cells: HashSet<Vector2<i32>>,
+    pub windows: Vec<TimeWindow>,
+}
+
+impl KeepOutZone {
+    pub fn new(cells: impl IntoIterator<Item = Vector2<i32>>, windows: Vec<TimeWindow>) -> Self {
+        Self {
+            cells: cells.into_iter().collect(),
+            windows,
+        }
+    }
+
+    pub fn is_active(&self, time: f64) -> bool {
+        self.windows.iter().any(|window| window.contains(time))
+    }
+
+    /// # Returns
+    /// Whether `cell` is in the zone and the zone is active at `time`.
+    pub fn is_kept_out(&self, cell: Vector2<i32>, time: f64) -> bool {
+        self.cells.contains(&cell) && self.is_active(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_windows() {
+        let auto = TimeWindow::new(0.0, 15.0);
+        let endgame = TimeWindow::new(130.0, 150.0);
+        let zone = KeepOutZone::new([Vector2::new(1, 1)], vec![auto, endgame]);
+
+        assert!(zone.is_kept_out(Vector2::new(1, 1), 0.0));
+        assert!(!zone.is_kept_out(Vector2::new(1, 1), 15.0));
+        assert!(zone.is_kept_out(Vector2::new(1, 1), 140.0));
+        assert!(!zone.is_kept_out(Vector2::new(2, 1), 140.0));
+        assert!(TimeWindow::ALWAYS.contains(-1e9));
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+
+use nalgebra::Vector2;
+
+/// How strongly observations move a cell's belief, in log-odds (ln(p / (1 - p))).
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogOddsConfig {
+    /// Added when a cell is observed occupied
+    pub hit: f32,
+    /// Added when a ray passes through a cell, usually negative
+    pub miss: f32,
+    /// The belief is clamped to this range so a cell can always change its mind quickly
+    pub min: f32,
+    pub max: f32,
+    /// Cells above this become obstacles
+    pub occupied_threshold: f32,
+    /// Obstacles below this become free again. Keeping it under `occupied_threshold` stops cells
+    /// near the threshold from flickering.
+    pub free_threshold: f32,
+}
+
+impl Default for LogOddsConfig {
+    /// Hits at p = 0.7, misses at p = 0.4, clamped to 0.12..0.97, obstacle above 0.75 and free
+    /// again below 0.5. A single hit is not enough to block a cell.
+    fn default() -> Self {
+        Self {
+            hit: get_log_odds(0.7),
+            miss: get_log_odds(0.4),
+            min: get_log_odds(0.12),
+            max: get_log_odds(0.97),
+            occupied_threshold: get_log_odds(0.75),
+            free_threshold: get_log_odds(0.5),
+        }
+    }
+}
+
+pub fn get_log_odds(probability: f32) -> f32 {
+    (probability / (1.0 - probability)).ln()
+}
+
+pub fn get_probability(log_odds: f32) -> f32 {
+    1.0 - 1.0 / (1.0 + log_odds.exp())
+}
+
+/// The occupancy belief of every observed cell. Cells that were never observed are at 0 (p = 0.5).
+#[derive(Clone, Default)]
+pub struct LogOddsGrid {
+    config: LogOddsConfig,
+    log_odds: HashMap<Vector2<i32>, f32>,
+    occupied: HashSet<Vector2<i32>>,
+}
+
+impl LogOddsGrid {
+    pub fn new(config: LogOddsConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_config(&self) -> &LogOddsConfig {
+        &self.config
+    }
+
+    /// # Returns
+    /// Whether the cell turned into an obstacle or stopped being one.
+    pub fn integrate_hit(&mut self, cell: Vector2<i32>) -> bool {
+        self.integrate(cell, self.config.hit)
+    }
+
+    /// # Returns
+    /// Whether the cell turned into an obstacle or stopped being one.
+    pub fn integrate_miss(&mut self, cell: Vector2<i32>) -> bool {
+        self.integrate(cell, self.config.miss)
+    }
+
+    fn integrate(&mut self, cell: Vector2<i32>, change: f32) -> bool {
+        let log_odds = self.log_odds.entry(cell).or_insert(0.0);
+        *log_odds = (*log_odds + change).clamp(self.config.min, self.config.max);
+
+        if *log_odds > self.config.occupied_threshold {
+            self.occupied.insert(cell)
+        } else if *log_odds < self.config.free_threshold {
+            self.occupied.remove(&cell)
+        } else {
+            false
+        }
+    }
+
+    pub fn get_log_odds(&self, cell: Vector2<i32>) -> f32 {
+        self.log_odds.get(&cell).copied().unwrap_or(0.0)
+    }
+
+    pub fn get_probability(&self, cell: Vector2<i32>) -> f32 {
+        get_probability(self.get_log_odds(cell))
+    }
+
+    pub fn is_occupied(&self, cell: Vector2<i32>) -> bool {
+        self.occupied.contains(&cell)
+    }
+
+    pub fn get_occupied_cells(&self) -> &HashSet<Vector2<i32>> {
+        &self.occupied
+    }
+
+    pub fn is_observed(&self, cell: Vector2<i32>) -> bool {
+        self.log_odds.contains_key(&cell)
+    }
+
+    /// Forgets every cell for which `keep` is false.
+    pub fn retain(&mut self, keep: impl Fn(&Vector2<i32>) -> bool) {
+        self.log_odds.retain(|cell, _| keep(cell));
+        self.occupied.retain(|cell| keep(cell));
+    }
+
+    /// # Returns
+    /// The same beliefs with every cell moved by `map`, which must not send two cells to one.
+    pub fn map_cells(&self, map: impl Fn(Vector2<i32>) -> Vector2<i32>) -> Self {
+        Self {
+            config: self.config.clone(),
+            log_odds: self
+                .log_odds
+                .iter()
+                .map(|(cell, log_odds)| (map(*cell), *log_odds))
+                .collect(),
+            occupied: self.occupied.iter().map(|cell| map(*cell)).collect(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.log_odds.clear();
+        self.occupied.clear();
+    }
+}
+
+/// # Returns
+/// The cells on the line from `start` to `end`, both included, in order (Bresenham).
+pub fn get_ray_cells(start: Vector2<i32>, end: Vector2<i32>) -> Vec<Vector2<i32>> {
+    let delta = end - start;
+    let step = Vector2::new(delta.x.signum(), delta.y.signum());
+    let dx = delta.x.abs();
+    let dy = -delta.y.abs();
+
+    let mut cells = Vec::with_capacity(dx.max(-dy) as usize + 1);
+    let mut current = start;
+    let mut error = dx + dy;
+    loop {
+        cells.push(current);
+        if current == end {
+            return cells;
+        }
+
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            current.x += step.x;
+        }
+        if doubled <= dx {
+            error += dx;
+            current.y += step.y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noisy_hit_is_forgotten() {
+        let mut grid = LogOddsGrid::new(LogOddsConfig::default());
+        let cell = Vector2::new(3, 4);
+
+        grid.integrate_hit(cell);
+        assert!(!grid.is_occupied(cell));
+        assert!(grid.integrate_hit(cell));
+        assert!(grid.is_occupied(cell));
+
+        // Hysteresis keeps it blocked until the belief drops back below p = 0.5
+        for _ in 0..4 {
+            grid.integrate_miss(cell);
+        }
+        assert!(grid.is_occupied(cell));
+        assert!(grid.integrate_miss(cell));
+        assert!(!grid.is_occupied(cell));
+    }
+
+    #[test]
+    fn test_clamping() {
+        let mut grid = LogOddsGrid::new(LogOddsConfig::default());
+        let cell = Vector2::new(0, 0);
+
+        for _ in 0..100 {
+            grid.integrate_hit(cell);
+        }
+        assert!((grid.get_probability(cell) - 0.97).abs() < 1e-3);
+
+        // A clamped cell still clears after a handful of misses
+        let misses = (0..20)
+            .take_while(|_| {
+                grid.integrate_miss(cell);
+                grid.is_occupied(cell)
+            })
+            .count();
+        assert!(misses < 10);
+    }
+
+    #[test]
+    fn test_ray_cells() {
+        let cells = get_ray_cells(Vector2::new(0, 0), Vector2::new(4, -2));
+
+        assert_eq!(cells.first(), Some(&Vector2::new(0, 0)));
+        assert_eq!(cells.last(), Some(&Vector2::new(4, -2)));
+        assert_eq!(cells.len(), 5);
+        assert!(cells
+            .windows(2)
+            .all(|pair| (pair[1] - pair[0]).abs().max() == 1));
+    }
+}
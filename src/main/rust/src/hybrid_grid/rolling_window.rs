@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use nalgebra::Vector2;
+
+use super::HybridGrid;
+
+/// A small grid that follows the robot around, e.g. at a finer resolution than the field grid for
+/// reactive avoidance. Cells keep their field coordinates at the window's resolution, so moving the
+/// window only forgets what falls out of it and everything else stays where it is on the field.
+pub struct RollingWindow {
+    grid: Arc<HybridGrid>,
+    /// Whether cells entering the window start out unknown instead of free
+    mark_entering_unknown: bool,
+}
+
+impl RollingWindow {
+    /// # Arguments
+    /// * `size_x` - The width of the window in cells
+    /// * `size_y` - The height of the window in cells
+    /// * `square_size_meters` - The size of one cell in meters
+    /// * `robot` - The robot position in field meters, which the window is centered on
+    /// * `mark_entering_unknown` - Whether cells entering the window start out unknown
+    pub fn new(
+        size_x: i32,
+        size_y: i32,
+        square_size_meters: f32,
+        robot: Vector2<f32>,
+        mark_entering_unknown: bool,
+    ) -> Self {
+        let mut grid = HybridGrid::new_raw(size_x, size_y, square_size_meters, 0, 0);
+        grid.set_center(grid.meters_to_cell(robot));
+
+        let mut window = Self {
+            grid: Arc::new(grid),
+            mark_entering_unknown,
+        };
+        if mark_entering_unknown {
+            let (min, max) = window.grid.get_cell_bounds();
+            let cells = get_cells_between(min, max, |_| true);
+            window.get_grid_mut().set_unknown_cells(&cells, true);
+        }
+
+        window
+    }
+
+    pub fn get_grid(&self) -> &HybridGrid {
+        &self.grid
+    }
+
+    /// # Returns
+    /// The grid for editing, copied first if a snapshot of it is still shared.
+    pub fn get_grid_mut(&mut self) -> &mut HybridGrid {
+        Arc::make_mut(&mut self.grid)
+    }
+
+    pub fn get_grid_snapshot(&self) -> Arc<HybridGrid> {
+        self.grid.clone()
+    }
+
+    /// Moves the window so it is centered on the robot again.
+    /// # Returns
+    /// Whether the window moved.
+    /// # Arguments
+    /// * `robot` - The robot position in field meters
+    pub fn recenter(&mut self, robot: Vector2<f32>) -> bool {
+        let center = self.grid.meters_to_cell(robot);
+        if center == self.grid.get_center() {
+            return false;
+        }
+
+        let (old_min, old_max) = self.grid.get_cell_bounds();
+        self.get_grid_mut().set_center(center);
+
+        if self.mark_entering_unknown {
+            let (min, max) = self.grid.get_cell_bounds();
+            let entering = get_cells_between(min, max, |cell| {
+                cell.x < old_min.x || cell.x > old_max.x || cell.y < old_min.y || cell.y > old_max.y
+            });
+            self.get_grid_mut().set_unknown_cells(&entering, true);
+        }
+
+        true
+    }
+}
+
+fn get_cells_between(
+    min: Vector2<i32>,
+    max: Vector2<i32>,
+    keep: impl Fn(&Vector2<i32>) -> bool,
+) -> Vec<Vector2<i32>> {
+    (min.x..=max.x)
+        .flat_map(|x| (min.y..=max.y).map(move |y| Vector2::new(x, y)))
+        .filter(keep)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recenter_discards_cells() {
+        let mut window = RollingWindow::new(10, 10, 0.1, Vector2::new(1.05, 1.05), false);
+        assert_eq!(window.get_grid().get_center(), Vector2::new(10, 10));
+
+        window
+            .get_grid_mut()
+            .push_static_obstacle(Vector2::new(6, 10));
+        window
+            .get_grid_mut()
+            .push_static_obstacle(Vector2::new(12, 10));
+
+        assert!(!window.recenter(Vector2::new(1.08, 1.02)));
+        assert!(window.recenter(Vector2::new(1.35, 1.05)));
+
+        let grid = window.get_grid();
+        assert_eq!(grid.get_center(), Vector2::new(13, 10));
+        assert!(!grid.is_obstructed(Vector2::new(6, 10)));
+        assert!(grid.is_obstructed(Vector2::new(12, 10)));
+    }
+
+    #[test]
+    fn test_entering_cells_are_unknown() {
+        let mut window = RollingWindow::new(4, 4, 0.5, Vector2::new(0.0, 0.0), true);
+        assert_eq!(window.get_grid().get_unknown_cells().len(), 16);
+
+        let known: Vec<Vector2<i32>> = window
+            .get_grid()
+            .get_unknown_cells()
+            .iter()
+            .copied()
+            .collect();
+        window.get_grid_mut().set_unknown_cells(&known, false);
+
+        window.recenter(Vector2::new(0.5, 0.0));
+        let grid = window.get_grid();
+        assert_eq!(grid.get_unknown_cells().len(), 4);
+        assert!(grid.get_unknown_cells().iter().all(|cell| cell.x == 2));
+    }
+}
@@ -0,0 +1,371 @@
+use nalgebra::{Rotation2, Vector2};
+
+use super::HybridGrid;
+
+/// Which cells a shape claims when it is rasterized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coverage {
+    /// Every cell the shape touches at all
+    Conservative,
+    /// Only the cells whose center lies inside the shape
+    Exact,
+}
+
+/// An obstacle outline in field meters.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObstacleShape {
+    /// A simple polygon, convex or concave, given by its corners in order
+    Polygon(Vec<Vector2<f32>>),
+    Circle {
+        center: Vector2<f32>,
+        radius: f32,
+    },
+    /// A rectangle of `size` turned counterclockwise by `rotation` radians about its center
+    Rectangle {
+        center: Vector2<f32>,
+        size: Vector2<f32>,
+        rotation: f32,
+    },
+    /// A line segment widened to `thickness` with round ends
+    Segment {
+        start: Vector2<f32>,
+        end: Vector2<f32>,
+        thickness: f32,
+    },
+}
+
+impl ObstacleShape {
+    pub fn contains_point(&self, point: Vector2<f32>) -> bool {
+        match self {
+            ObstacleShape::Polygon(corners) => polygon_contains_point(corners, point),
+            ObstacleShape::Circle { center, radius } => (point - center).norm() <= *radius,
+            ObstacleShape::Rectangle { .. } => polygon_contains_point(&self.get_corners(), point),
+            ObstacleShape::Segment {
+                start,
+                end,
+                thickness,
+            } => get_distance_to_segment(point, *start, *end) <= thickness / 2.0,
+        }
+    }
+
+    /// # Returns
+    /// Whether the shape overlaps the axis aligned box from `min` to `max` anywhere.
+    pub fn intersects_box(&self, min: Vector2<f32>, max: Vector2<f32>) -> bool {
+        match self {
+            ObstacleShape::Polygon(corners) => polygon_intersects_box(corners, min, max),
+            ObstacleShape::Circle { center, radius } => {
+                (center.sup(&min).inf(&max) - center).norm() <= *radius
+            }
+            ObstacleShape::Rectangle { .. } => {
+                polygon_intersects_box(&self.get_corners(), min, max)
+            }
+            ObstacleShape::Segment {
+                start,
+                end,
+                thickness,
+            } => get_segment_box_distance(*start, *end, min, max) <= thickness / 2.0,
+        }
+    }
+
+    /// # Returns
+    /// The smallest axis aligned box around the shape as (min, max).
+    pub fn get_bounds(&self) -> (Vector2<f32>, Vector2<f32>) {
+        match self {
+            ObstacleShape::Circle { center, radius } => (
+                center - Vector2::repeat(*radius),
+                center + Vector2::repeat(*radius),
+            ),
+            ObstacleShape::Segment {
+                start,
+                end,
+                thickness,
+            } => (
+                start.inf(end) - Vector2::repeat(thickness / 2.0),
+                start.sup(end) + Vector2::repeat(thickness / 2.0),
+            ),
+            _ => {
+                let corners = self.get_corners();
+                let first = corners.first().copied().unwrap_or_else(Vector2::zeros);
+                corners.iter().fold((first, first), |(min, max), corner| {
+                    (min.inf(corner), max.sup(corner))
+                })
+            }
+        }
+    }
+
+    /// # Returns
+    /// The corners of a polygon or rectangle, and nothing for other shapes.
+    pub fn get_corners(&self) -> Vec<Vector2<f32>> {
+        match self {
+            ObstacleShape::Polygon(corners) => corners.clone(),
+            ObstacleShape::Rectangle {
+                center,
+                size,
+                rotation,
+            } => {
+                let rotation = Rotation2::new(*rotation);
+                let half = size / 2.0;
+                [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                    .iter()
+                    .map(|(x, y)| center + rotation * Vector2::new(x * half.x, y * half.y))
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// # Returns
+    /// Every cell of `grid` the shape covers, clipped to the grid.
+    pub fn rasterize(&self, grid: &HybridGrid, coverage: Coverage) -> Vec<Vector2<i32>> {
+        let (min, max) = self.get_bounds();
+        let (grid_min, grid_max) = grid.get_cell_bounds();
+        let min_cell = grid.meters_to_cell(min).sup(&grid_min);
+        let max_cell = grid.meters_to_cell(max).inf(&grid_max);
+        let square_size = grid.get_square_size_meters();
+
+        let mut cells = Vec::new();
+        for x in min_cell.x..=max_cell.x {
+            for y in min_cell.y..=max_cell.y {
+                let cell = Vector2::new(x, y);
+                let covered = match coverage {
+                    Coverage::Exact => self.contains_point(grid.cell_to_meters(cell)),
+                    Coverage::Conservative => {
+                        let cell_min = cell.cast::<f32>() * square_size;
+                        self.intersects_box(cell_min, cell_min + Vector2::repeat(square_size))
+                    }
+                };
+
+                if covered {
+                    cells.push(cell);
+                }
+            }
+        }
+
+        cells
+    }
+}
+
+/// Even-odd rule, so concave and self-touching outlines work as expected.
+fn polygon_contains_point(corners: &[Vector2<f32>], point: Vector2<f32>) -> bool {
+    let mut inside = false;
+    let mut previous = match corners.last() {
+        Some(last) => *last,
+        None => return false,
+    };
+
+    for corner in corners {
+        if (corner.y > point.y) != (previous.y > point.y) {
+            let crossing_x =
+                corner.x + (point.y - corner.y) / (previous.y - corner.y) * (previous.x - corner.x);
+            if point.x < crossing_x {
+                inside = !inside;
+            }
+        }
+        previous = *corner;
+    }
+
+    inside
+}
+
+fn polygon_intersects_box(corners: &[Vector2<f32>], min: Vector2<f32>, max: Vector2<f32>) -> bool {
+    if corners.is_empty() {
+        return false;
+    }
+
+    if corners.iter().any(|corner| is_in_box(*corner, min, max))
+        || polygon_contains_point(corners, (min + max) / 2.0)
+    {
+        return true;
+    }
+
+    (0..corners.len()).any(|i| {
+        let next = corners[(i + 1) % corners.len()];
+        segment_intersects_box(corners[i], next, min, max)
+    })
+}
+
+fn is_in_box(point: Vector2<f32>, min: Vector2<f32>, max: Vector2<f32>) -> bool {
+    point.x >= min.x && point.x <= max.x && point.y >= min.y && point.y <= max.y
+}
+
+fn segment_intersects_box(
+    start: Vector2<f32>,
+    end: Vector2<f32>,
+    min: Vector2<f32>,
+    max: Vector2<f32>,
+) -> bool {
+    clip_segment_to_box(start, end, min, max).is_some()
+}
+
+/// Liang-Barsky clipping of the segment against the box.
+/// # Returns
+/// The part of the segment inside the box, with an end that is already inside kept exactly, or
+/// `None` if the segment misses the box.
+pub(super) fn clip_segment_to_box(
+    start: Vector2<f32>,
+    end: Vector2<f32>,
+    min: Vector2<f32>,
+    max: Vector2<f32>,
+) -> Option<(Vector2<f32>, Vector2<f32>)> {
+    let direction = end - start;
+    let mut t_min = 0.0f32;
+    let mut t_max = 1.0f32;
+
+    for axis in 0..2 {
+        if direction[axis].abs() < f32::EPSILON {
+            if start[axis] < min[axis] || start[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let t_near = (min[axis] - start[axis]) / direction[axis];
+        let t_far = (max[axis] - start[axis]) / direction[axis];
+        t_min = t_min.max(t_near.min(t_far));
+        t_max = t_max.min(t_near.max(t_far));
+        if t_min > t_max {
+            return None;
+        }
+    }
+
+    let clipped_start = if t_min > 0.0 {
+        start + direction * t_min
+    } else {
+        start
+    };
+    let clipped_end = if t_max < 1.0 {
+        start + direction * t_max
+    } else {
+        end
+    };
+    Some((clipped_start, clipped_end))
+}
+
+pub(super) fn get_distance_to_segment(
+    point: Vector2<f32>,
+    start: Vector2<f32>,
+    end: Vector2<f32>,
+) -> f32 {
+    let direction = end - start;
+    let length_squared = direction.norm_squared();
+    if length_squared == 0.0 {
+        return (point - start).norm();
+    }
+
+    let t = ((point - start).dot(&direction) / length_squared).clamp(0.0, 1.0);
+    (point - (start + direction * t)).norm()
+}
+
+fn get_segment_box_distance(
+    start: Vector2<f32>,
+    end: Vector2<f32>,
+    min: Vector2<f32>,
+    max: Vector2<f32>,
+) -> f32 {
+    if segment_intersects_box(start, end, min, max) {
+        return 0.0;
+    }
+
+    let box_corners = [
+        min,
+        Vector2::new(max.x, min.y),
+        max,
+        Vector2::new(min.x, max.y),
+    ];
+    let to_box = [start, end]
+        .into_iter()
+        .map(|point| (point.sup(&min).inf(&max) - point).norm());
+    let to_segment = box_corners
+        .iter()
+        .map(|corner| get_distance_to_segment(*corner, start, end));
+
+    to_box.chain(to_segment).fold(f32::INFINITY, f32::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_4;
+
+    fn create_grid() -> HybridGrid {
+        HybridGrid::new(20, 20, 0.5, vec![], 10, 10)
+    }
+
+    #[test]
+    fn test_circle_coverage() {
+        let circle = ObstacleShape::Circle {
+            center: Vector2::new(5.0, 5.0),
+            radius: 0.9,
+        };
+
+        let exact = circle.rasterize(&create_grid(), Coverage::Exact);
+        let conservative = circle.rasterize(&create_grid(), Coverage::Conservative);
+
+        assert_eq!(exact.len(), 12);
+        assert_eq!(conservative.len(), 16);
+        assert!(exact.iter().all(|cell| conservative.contains(cell)));
+    }
+
+    #[test]
+    fn test_concave_polygon() {
+        // An L shape whose notch covers the upper right quarter
+        let polygon = ObstacleShape::Polygon(vec![
+            Vector2::new(1.0, 1.0),
+            Vector2::new(3.0, 1.0),
+            Vector2::new(3.0, 2.0),
+            Vector2::new(2.0, 2.0),
+            Vector2::new(2.0, 3.0),
+            Vector2::new(1.0, 3.0),
+        ]);
+        let mut grid = create_grid();
+
+        assert_eq!(grid.add_obstacle_shape(&polygon, Coverage::Exact), 12);
+        assert!(grid.is_obstructed(grid.meters_to_cell(Vector2::new(1.5, 2.5))));
+        assert!(grid.is_obstructed(grid.meters_to_cell(Vector2::new(2.5, 1.5))));
+        assert!(!grid.is_obstructed(grid.meters_to_cell(Vector2::new(2.5, 2.5))));
+    }
+
+    #[test]
+    fn test_rotated_rectangle() {
+        let diamond = ObstacleShape::Rectangle {
+            center: Vector2::new(5.0, 5.0),
+            size: Vector2::new(2.0, 2.0),
+            rotation: FRAC_PI_4,
+        };
+
+        assert!(diamond.contains_point(Vector2::new(5.0, 6.3)));
+        assert!(!diamond.contains_point(Vector2::new(5.9, 5.9)));
+
+        let cells = diamond.rasterize(&create_grid(), Coverage::Exact);
+        assert!(cells.contains(&Vector2::new(10, 11)));
+        assert!(!cells.contains(&Vector2::new(11, 11)));
+    }
+
+    #[test]
+    fn test_thick_segment() {
+        let segment = ObstacleShape::Segment {
+            start: Vector2::new(1.0, 1.25),
+            end: Vector2::new(4.0, 1.25),
+            thickness: 0.2,
+        };
+
+        let exact = segment.rasterize(&create_grid(), Coverage::Exact);
+        let conservative = segment.rasterize(&create_grid(), Coverage::Conservative);
+
+        assert_eq!(exact.len(), 6);
+        assert!(exact.iter().all(|cell| cell.y == 2));
+        assert_eq!(conservative.len(), 8);
+    }
+
+    #[test]
+    fn test_clipped_to_grid() {
+        let circle = ObstacleShape::Circle {
+            center: Vector2::new(0.0, 0.0),
+            radius: 3.0,
+        };
+
+        let cells = circle.rasterize(&create_grid(), Coverage::Conservative);
+        assert!(!cells.is_empty());
+        assert!(cells.iter().all(|cell| cell.x >= 0 && cell.y >= 0));
+    }
+}
@@ -0,0 +1,114 @@
+use std::sync::{Arc, RwLock};
+
+use super::HybridGrid;
+
+/// An immutable view of a `SharedGrid` at one version. Holding a snapshot never blocks writers.
+#[derive(Clone)]
+pub struct GridSnapshot {
+    grid: Arc<HybridGrid>,
+    version: u64,
+}
+
+impl GridSnapshot {
+    pub fn get_grid(&self) -> &Arc<HybridGrid> {
+        &self.grid
+    }
+
+    pub fn get_version(&self) -> u64 {
+        self.version
+    }
+}
+
+/// A grid that several planners can read while it is being updated. Readers take snapshots,
+/// and writers copy the grid only if a snapshot of the current version is still held somewhere,
+/// so a planner always sees one consistent state for its whole search.
+pub struct SharedGrid {
+    current: RwLock<GridSnapshot>,
+}
+
+impl SharedGrid {
+    pub fn new(grid: HybridGrid) -> Self {
+        Self {
+            current: RwLock::new(GridSnapshot {
+                grid: Arc::new(grid),
+                version: 0,
+            }),
+        }
+    }
+
+    /// # Returns
+    /// The current version of the grid.
+    pub fn snapshot(&self) -> GridSnapshot {
+        self.current
+            .read()
+            .expect("Shared grid lock poisoned")
+            .clone()
+    }
+
+    /// Applies `edit` to a new version of the grid. Snapshots taken before keep seeing the old one.
+    pub fn update<R>(&self, edit: impl FnOnce(&mut HybridGrid) -> R) -> R {
+        let mut current = self.current.write().expect("Shared grid lock poisoned");
+        let result = edit(Arc::make_mut(&mut current.grid));
+        current.version += 1;
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector2;
+    use std::thread;
+
+    #[test]
+    fn test_snapshot_is_unaffected_by_updates() {
+        let shared = SharedGrid::new(HybridGrid::new_raw(100, 100, 1.0, 0, 0));
+        let before = shared.snapshot();
+
+        shared.update(|grid| grid.push_static_obstacle(Vector2::new(1, 1)));
+        let after = shared.snapshot();
+
+        assert!(!before.get_grid().is_obstructed(Vector2::new(1, 1)));
+        assert!(after.get_grid().is_obstructed(Vector2::new(1, 1)));
+        assert_eq!(before.get_version(), 0);
+        assert_eq!(after.get_version(), 1);
+    }
+
+    #[test]
+    fn test_update_without_readers_does_not_copy() {
+        let shared = SharedGrid::new(HybridGrid::new_raw(100, 100, 1.0, 0, 0));
+        let before = Arc::as_ptr(shared.snapshot().get_grid());
+
+        shared.update(|grid| grid.push_static_obstacle(Vector2::new(1, 1)));
+
+        assert_eq!(Arc::as_ptr(shared.snapshot().get_grid()), before);
+    }
+
+    #[test]
+    fn test_concurrent_readers_and_writer() {
+        let shared = Arc::new(SharedGrid::new(HybridGrid::new_raw(100, 100, 1.0, 0, 0)));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        let snapshot = shared.snapshot();
+                        let count = snapshot.get_grid().get_static_obstacles().len() as u64;
+                        assert_eq!(count, snapshot.get_version());
+                    }
+                })
+            })
+            .collect();
+
+        for i in 0..100 {
+            shared.update(|grid| grid.push_static_obstacle(Vector2::new(i, 0)));
+        }
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(shared.snapshot().get_version(), 100);
+    }
+}
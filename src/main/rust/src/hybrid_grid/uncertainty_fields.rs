@@ -0,0 +1,279 @@
+use nalgebra::{Matrix2, Rotation2, Vector2};
+
+/// How far out a Gaussian field reaches, in standard deviations.
+pub const GAUSSIAN_CUTOFF_SIGMAS: f32 = 3.0;
+
+/// The area an uncertainty field covers, around its center and in grid cells.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldShape {
+    Circle {
+        radius: f32,
+    },
+    /// `radii` along the field's own axes, which are turned counterclockwise by `rotation` radians
+    Ellipse {
+        radii: Vector2<f32>,
+        rotation: f32,
+    },
+    /// `size` along the field's own axes, turned like an ellipse
+    Rectangle {
+        size: Vector2<f32>,
+        rotation: f32,
+    },
+    /// A position estimate, reaching out to `GAUSSIAN_CUTOFF_SIGMAS` standard deviations
+    Gaussian {
+        covariance: Matrix2<f32>,
+    },
+}
+
+impl FieldShape {
+    /// # Returns
+    /// How far into the shape `offset` (from the center) is, 0 at the center and 1 on the edge, or
+    /// `None` if it is outside.
+    pub fn get_normalized_distance(&self, offset: Vector2<f32>) -> Option<f32> {
+        let distance = match self {
+            Self::Circle { radius } => offset.magnitude() / radius,
+            Self::Ellipse { radii, rotation } => {
+                let local = Rotation2::new(-rotation) * offset;
+                local.component_div(radii).magnitude()
+            }
+            Self::Rectangle { size, rotation } => {
+                let local = Rotation2::new(-rotation) * offset * 2.0;
+                local.component_div(size).abs().max()
+            }
+            Self::Gaussian { covariance } => {
+                let inverse = covariance.try_inverse()?;
+                offset.dot(&(inverse * offset)).sqrt() / GAUSSIAN_CUTOFF_SIGMAS
+            }
+        };
+
+        (distance <= 1.0).then_some(distance)
+    }
+
+    /// # Returns
+    /// The same shape with every length multiplied by `factor`.
+    pub fn scaled(&self, factor: f32) -> Self {
+        match self {
+            Self::Circle { radius } => Self::Circle {
+                radius: radius * factor,
+            },
+            Self::Ellipse { radii, rotation } => Self::Ellipse {
+                radii: radii * factor,
+                rotation: *rotation,
+            },
+            Self::Rectangle { size, rotation } => Self::Rectangle {
+                size: size * factor,
+                rotation: *rotation,
+            },
+            Self::Gaussian { covariance } => Self::Gaussian {
+                covariance: covariance * (factor * factor),
+            },
+        }
+    }
+
+    /// # Returns
+    /// The distance from the center to the furthest point of the shape.
+    pub fn get_bounding_radius(&self) -> f32 {
+        match self {
+            Self::Circle { radius } => *radius,
+            Self::Ellipse { radii, .. } => radii.max(),
+            Self::Rectangle { size, .. } => size.magnitude() / 2.0,
+            Self::Gaussian { covariance } => {
+                let largest_variance = covariance.symmetric_eigenvalues().max();
+                largest_variance.max(0.0).sqrt() * GAUSSIAN_CUTOFF_SIGMAS
+            }
+        }
+    }
+}
+
+/// How a field's cost changes from its center to its edge.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Falloff {
+    /// Full intensity at the center, fading to nothing at the edge
+    Linear,
+    /// Full intensity at the center, fading faster near it than `Linear`
+    Quadratic,
+    /// The normal density, so a Gaussian field with this falloff is the position estimate itself
+    Gaussian,
+    /// Full intensity everywhere inside
+    Step,
+    /// Nothing at the center, easing up to full intensity at the edge
+    #[default]
+    EaseOut,
+}
+
+impl Falloff {
+    /// # Returns
+    /// The share of the intensity left at `distance`, with 0 the center and 1 the edge.
+    pub fn apply(&self, distance: f32) -> f32 {
+        match self {
+            Self::Linear => 1.0 - distance,
+            Self::Quadratic => (1.0 - distance).powi(2),
+            Self::Gaussian => {
+                let sigmas = distance * GAUSSIAN_CUTOFF_SIGMAS;
+                (-0.5 * sigmas * sigmas).exp()
+            }
+            Self::Step => 1.0,
+            Self::EaseOut => 1.0 - (1.0 - distance).powi(2),
+        }
+    }
+
+    /// # Returns
+    /// The falloff with the given index in declaration order, `EaseOut` for anything else.
+    pub fn from_index(index: i32) -> Self {
+        match index {
+            0 => Self::Linear,
+            1 => Self::Quadratic,
+            2 => Self::Gaussian,
+            3 => Self::Step,
+            _ => Self::EaseOut,
+        }
+    }
+}
+
+/// A region of extra cost, in grid cells.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UncertentyField {
+    pub center: Vector2<f32>,
+    pub shape: FieldShape,
+    /// The full cost the falloff scales, when the source was last seen
+    pub intensity: f32,
+    pub falloff: Falloff,
+    /// When the source was last seen, in match time seconds
+    pub timestamp: f64,
+    /// How fast the intensity fades after `timestamp`, as a fraction per second (exponential)
+    pub decay_rate: f32,
+    /// How long after `timestamp` the field is removed, `None` to keep it until removed
+    pub time_to_live: Option<f64>,
+}
+
+impl UncertentyField {
+    /// A field with the default falloff that never fades.
+    pub fn new(center: Vector2<f32>, shape: FieldShape, intensity: f32) -> Self {
+        Self {
+            center,
+            shape,
+            intensity,
+            falloff: Falloff::default(),
+            timestamp: 0.0,
+            decay_rate: 0.0,
+            time_to_live: None,
+        }
+    }
+
+    /// # Returns
+    /// The intensity left at `time` after decaying since the source was last seen.
+    pub fn get_intensity(&self, time: f64) -> f32 {
+        let elapsed = (time - self.timestamp).max(0.0) as f32;
+        self.intensity * (-self.decay_rate * elapsed).exp()
+    }
+
+    pub fn is_expired(&self, time: f64) -> bool {
+        self.time_to_live
+            .is_some_and(|time_to_live| self.timestamp + time_to_live <= time)
+    }
+
+    /// # Returns
+    /// The cost the field adds at `position` at `time`, or `None` if the field doesn't cover it.
+    pub fn get_cost(&self, position: Vector2<f32>, time: f64) -> Option<f32> {
+        let distance = self.shape.get_normalized_distance(position - self.center)?;
+
+        Some(self.falloff.apply(distance) * self.get_intensity(time))
+    }
+}
+
+/// How the costs of overlapping uncertainty fields add up.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FieldCombine {
+    #[default]
+    Sum,
+    Max,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn test_shapes() {
+        let ellipse = FieldShape::Ellipse {
+            radii: Vector2::new(4.0, 1.0),
+            rotation: FRAC_PI_2,
+        };
+        assert!(ellipse
+            .get_normalized_distance(Vector2::new(3.0, 0.0))
+            .is_none());
+        assert!(
+            (ellipse
+                .get_normalized_distance(Vector2::new(0.0, 2.0))
+                .unwrap()
+                - 0.5)
+                .abs()
+                < 1e-6
+        );
+
+        let rectangle = FieldShape::Rectangle {
+            size: Vector2::new(4.0, 2.0),
+            rotation: 0.0,
+        };
+        assert_eq!(
+            rectangle.get_normalized_distance(Vector2::new(1.9, 0.9)),
+            Some(0.95)
+        );
+        assert!(rectangle
+            .get_normalized_distance(Vector2::new(0.0, 1.1))
+            .is_none());
+
+        // One standard deviation along x is 2 cells, along y 0.5
+        let gaussian = FieldShape::Gaussian {
+            covariance: Matrix2::new(4.0, 0.0, 0.0, 0.25),
+        };
+        let one_sigma = gaussian
+            .get_normalized_distance(Vector2::new(2.0, 0.0))
+            .unwrap();
+        assert!((one_sigma - 1.0 / GAUSSIAN_CUTOFF_SIGMAS).abs() < 1e-6);
+        assert!(gaussian
+            .get_normalized_distance(Vector2::new(0.0, 2.0))
+            .is_none());
+        assert!((gaussian.get_bounding_radius() - 6.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_falloffs() {
+        for falloff in [
+            Falloff::Linear,
+            Falloff::Quadratic,
+            Falloff::Gaussian,
+            Falloff::Step,
+        ] {
+            assert_eq!(falloff.apply(0.0), 1.0);
+        }
+
+        assert_eq!(Falloff::Linear.apply(0.5), 0.5);
+        assert_eq!(Falloff::Quadratic.apply(0.5), 0.25);
+        assert!(Falloff::Gaussian.apply(1.0) < 0.02);
+        assert_eq!(Falloff::Step.apply(1.0), 1.0);
+        assert_eq!(Falloff::EaseOut.apply(0.0), 0.0);
+        assert_eq!(Falloff::EaseOut.apply(0.5), 0.75);
+        assert_eq!(Falloff::EaseOut.apply(1.0), 1.0);
+        assert_eq!(Falloff::from_index(4), Falloff::default());
+    }
+
+    #[test]
+    fn test_decay() {
+        let mut field = UncertentyField::new(
+            Vector2::new(0.0, 0.0),
+            FieldShape::Circle { radius: 1.0 },
+            2.0,
+        );
+        field.timestamp = 10.0;
+        field.decay_rate = 0.5;
+        field.time_to_live = Some(4.0);
+
+        assert_eq!(field.get_intensity(5.0), 2.0);
+        assert!((field.get_intensity(12.0) - 2.0 * (-1.0f32).exp()).abs() < 1e-6);
+        assert!(!field.is_expired(13.9));
+        assert!(field.is_expired(14.0));
+    }
+}
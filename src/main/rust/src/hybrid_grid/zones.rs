@@ -0,0 +1,164 @@
+use nalgebra::Vector2;
+
+use super::{
+    shapes::{get_distance_to_segment, Coverage, ObstacleShape},
+    HybridGrid,
+};
+
+/// Where and facing which way a robot should stand to use a zone, e.g. in front of a scoring face.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ApproachPose {
+    /// In field meters
+    pub position: Vector2<f32>,
+    /// In radians counterclockwise from +x
+    pub heading: f32,
+}
+
+impl ApproachPose {
+    pub fn new(position: Vector2<f32>, heading: f32) -> Self {
+        Self { position, heading }
+    }
+}
+
+/// A named area of the field, so strategy code can talk about places like "reef_face_A" instead
+/// of cells.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SemanticZone {
+    pub name: String,
+    /// The outline in field meters
+    pub polygon: Vec<Vector2<f32>>,
+    /// Where to go to use the zone, the zone itself when empty
+    pub approach_poses: Vec<ApproachPose>,
+}
+
+impl SemanticZone {
+    pub fn new(name: impl Into<String>, polygon: Vec<Vector2<f32>>) -> Self {
+        Self {
+            name: name.into(),
+            polygon,
+            approach_poses: Vec::new(),
+        }
+    }
+
+    pub fn with_approach_poses(mut self, approach_poses: Vec<ApproachPose>) -> Self {
+        self.approach_poses = approach_poses;
+        self
+    }
+
+    pub fn get_shape(&self) -> ObstacleShape {
+        ObstacleShape::Polygon(self.polygon.clone())
+    }
+
+    /// # Arguments
+    /// * `point` - A position in field meters
+    pub fn contains_point(&self, point: Vector2<f32>) -> bool {
+        self.get_shape().contains_point(point)
+    }
+
+    /// # Returns
+    /// How far `point` is from the zone in meters, 0 if it is inside.
+    pub fn get_distance(&self, point: Vector2<f32>) -> f32 {
+        if self.contains_point(point) {
+            return 0.0;
+        }
+
+        (0..self.polygon.len())
+            .map(|i| {
+                let next = self.polygon[(i + 1) % self.polygon.len()];
+                get_distance_to_segment(point, self.polygon[i], next)
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// # Returns
+    /// The cells of `grid` whose center is inside the zone, or every cell it touches if it is too
+    /// small to hold a cell center.
+    pub fn get_cells(&self, grid: &HybridGrid) -> Vec<Vector2<i32>> {
+        let shape = self.get_shape();
+        let cells = shape.rasterize(grid, Coverage::Exact);
+        if cells.is_empty() {
+            return shape.rasterize(grid, Coverage::Conservative);
+        }
+
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_geometry() {
+        let zone = SemanticZone::new(
+            "station",
+            vec![
+                Vector2::new(1.0, 1.0),
+                Vector2::new(2.0, 1.0),
+                Vector2::new(2.0, 2.0),
+                Vector2::new(1.0, 2.0),
+            ],
+        );
+        assert!(zone.contains_point(Vector2::new(1.5, 1.5)));
+        assert_eq!(zone.get_distance(Vector2::new(1.5, 1.5)), 0.0);
+        assert_eq!(zone.get_distance(Vector2::new(4.0, 1.5)), 2.0);
+
+        let grid = HybridGrid::new_raw(10, 10, 0.5, 5, 5);
+        let mut cells = zone.get_cells(&grid);
+        cells.sort_by_key(|cell| (cell.x, cell.y));
+        assert_eq!(
+            cells,
+            vec![
+                Vector2::new(2, 2),
+                Vector2::new(2, 3),
+                Vector2::new(3, 2),
+                Vector2::new(3, 3)
+            ]
+        );
+
+        let tiny = SemanticZone::new(
+            "tiny",
+            vec![
+                Vector2::new(1.1, 1.1),
+                Vector2::new(1.2, 1.1),
+                Vector2::new(1.2, 1.2),
+            ],
+        );
+        assert_eq!(tiny.get_cells(&grid), vec![Vector2::new(2, 2)]);
+    }
+
+    #[test]
+    fn test_zone_queries() {
+        let mut grid = HybridGrid::new_raw(10, 10, 0.5, 5, 5);
+        let square = |name: &str, min: f32| {
+            SemanticZone::new(
+                name,
+                vec![
+                    Vector2::new(min, 0.0),
+                    Vector2::new(min + 1.0, 0.0),
+                    Vector2::new(min + 1.0, 1.0),
+                    Vector2::new(min, 1.0),
+                ],
+            )
+        };
+        grid.set_semantic_zone(square("reef_face_A", 0.0));
+        grid.set_semantic_zone(square("coral_station_left", 3.0));
+        grid.set_semantic_zone(square("overlap", 0.5));
+
+        let names: Vec<&str> = grid
+            .get_semantic_zones_at(Vector2::new(0.75, 0.5))
+            .iter()
+            .map(|zone| zone.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["overlap", "reef_face_A"]);
+
+        let (nearest, distance) = grid
+            .get_nearest_semantic_zone(Vector2::new(2.75, 0.5))
+            .unwrap();
+        assert_eq!(nearest.name, "coral_station_left");
+        assert_eq!(distance, 0.25);
+
+        assert!(grid.remove_semantic_zone("overlap").is_some());
+        assert!(grid.get_semantic_zone("overlap").is_none());
+    }
+}
@@ -30,3 +30,9 @@ pub fn from_jfloat_array_to_vector2_float(env: &JNIEnv, array: JFloatArray) -> V
     env.get_float_array_region(array, 0, &mut buffer).unwrap();
     Vector2::new(buffer[0], buffer[1])
 }
+
+pub fn from_jint_array_to_vector2_int_vec(env: &JNIEnv, array: JIntArray) -> Vec<Vector2<i32>> {
+    let buffer = jintarray_to_vec(env, array);
+    let (pairs, _) = buffer.as_chunks::<2>();
+    pairs.iter().map(|[x, y]| Vector2::new(*x, *y)).collect()
+}
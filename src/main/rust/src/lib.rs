@@ -1,45 +1,102 @@
-use hybrid_grid::HybridGrid;
+use std::sync::{Arc, Mutex};
+
+use field_map::{presets::FieldPreset, FieldMap};
+use hybrid_grid::{
+    alliance::{AllianceTransform, FieldSymmetry},
+    hybrid_objects::{HybridObject, ObjectClass},
+    keep_out::{KeepOutZone, TimeWindow},
+    shapes::{Coverage, ObstacleShape},
+    shared_grid::SharedGrid,
+    uncertainty_fields::{Falloff, FieldShape, UncertentyField},
+    zones::{ApproachPose, SemanticZone},
+    HybridGrid,
+};
 use jni::{
-    objects::{JClass, JFloatArray, JIntArray},
-    sys::{jfloat, jint, jlong},
+    objects::{JClass, JFloatArray, JIntArray, JObject, JObjectArray, JString, JValue},
+    sys::{jboolean, jdouble, jfloat, jint, jlong},
     JNIEnv,
 };
-use nalgebra::Vector2;
+use lazy_static::lazy_static;
+use nalgebra::{Matrix2, Vector2};
 use pathfinding::{
     a_star::{node::NodePickStyle, AStar},
-    NodeRadiusSearch, Pathfinding,
+    alternatives::{self, AlternativePathConfig},
+    batch::BatchPlanner,
+    costmap::{ClassAvoidance, Costmap, ObjectAvoidance, UnknownPolicy},
+    via_points, zones, NodeRadiusSearch, PathCost, Pathfinding,
 };
 
+pub mod field_map;
 pub mod hybrid_grid;
 pub mod jni_util_extended;
 pub mod pathfinding;
 pub mod time_structures;
 pub mod trajectory_maker;
 
-fn get_astar<'a>(env: &mut JNIEnv<'a>, obj: JClass<'a>) -> &'a mut AStar {
+lazy_static! {
+    static ref BATCH_PLANNER: Mutex<BatchPlanner> = Mutex::new(BatchPlanner::new(
+        std::thread::available_parallelism().map_or(1, |count| count.get())
+    ));
+}
+
+/// The native side of an `AStarPathfinder`. Java may edit the grid from one thread while planning
+/// on another, so the grid is shared and every search runs on the snapshot it started with.
+struct NativePathfinder {
+    grid: SharedGrid,
+    pick_style: NodePickStyle,
+    node_radius_search_config: NodeRadiusSearch,
+    unknown_policy: Mutex<UnknownPolicy>,
+    /// `None` until a class is configured, keeping hybrid objects in the clearance term
+    object_avoidance: Mutex<Option<ObjectAvoidance>>,
+    /// Cells per second for estimating when cells are reached, `None` to check them all now
+    travel_speed: Mutex<Option<f64>>,
+}
+
+impl NativePathfinder {
+    fn get_planner(&self) -> AStar {
+        let config = self.node_radius_search_config.clone();
+        let costmap = match self
+            .object_avoidance
+            .lock()
+            .expect("Object avoidance lock poisoned")
+            .clone()
+        {
+            Some(avoidance) => Costmap::from_object_avoidance(config, avoidance),
+            None => Costmap::from_node_radius_search(config),
+        };
+        let mut astar = AStar::build_with_costmap(
+            self.grid.snapshot().get_grid().clone(),
+            self.pick_style.clone(),
+            costmap,
+        );
+        astar.set_unknown_policy(
+            *self
+                .unknown_policy
+                .lock()
+                .expect("Unknown policy lock poisoned"),
+        );
+        astar.set_travel_speed(
+            *self
+                .travel_speed
+                .lock()
+                .expect("Travel speed lock poisoned"),
+        );
+
+        astar
+    }
+}
+
+fn get_native_pathfinder<'a>(env: &mut JNIEnv<'a>, obj: &JClass<'a>) -> &'a NativePathfinder {
     let ptr = env
         .get_field(obj, "nativePtr", "J")
         .expect("Field not found")
         .j()
         .unwrap();
 
-    let astar = unsafe { &mut *(ptr as *mut AStar) };
-    astar
+    unsafe { &*(ptr as *const NativePathfinder) }
 }
 
-#[no_mangle]
-pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_calculate<'a>(
-    mut env: JNIEnv<'a>,
-    obj: JClass<'a>,
-    start_x_y: JIntArray<'a>,
-    end_x_y: JIntArray<'a>,
-) -> JIntArray<'a> {
-    let start = jni_util_extended::from_jint_array_to_vector2_int(&env, start_x_y);
-    let end = jni_util_extended::from_jint_array_to_vector2_int(&env, end_x_y);
-
-    let astar = get_astar(&mut env, obj);
-
-    let path = astar.calculate_path(start, end);
+fn path_to_jint_array<'a>(env: &mut JNIEnv<'a>, path: Option<Vec<Vector2<i32>>>) -> JIntArray<'a> {
     if path.is_none() {
         return env
             .new_int_array(0)
@@ -64,19 +121,810 @@ pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_calculate<
     return jint_array;
 }
 
+fn paths_to_jobject_array<'a>(
+    env: &mut JNIEnv<'a>,
+    paths: Vec<Vec<Vector2<i32>>>,
+) -> JObjectArray<'a> {
+    let object_array = env
+        .new_object_array(paths.len() as i32, "[I", JObject::null())
+        .expect("Failed to create Java int[] array");
+    for (i, path) in paths.into_iter().enumerate() {
+        let jint_array = path_to_jint_array(env, Some(path));
+        env.set_object_array_element(&object_array, i as i32, jint_array)
+            .expect("Failed to copy path into Java int[] array");
+    }
+
+    object_array
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_calculate<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    start_x_y: JIntArray<'a>,
+    end_x_y: JIntArray<'a>,
+) -> JIntArray<'a> {
+    let start = jni_util_extended::from_jint_array_to_vector2_int(&env, start_x_y);
+    let end = jni_util_extended::from_jint_array_to_vector2_int(&env, end_x_y);
+
+    let astar = get_native_pathfinder(&mut env, &obj).get_planner();
+
+    let path = astar.calculate_path(start, end);
+    path_to_jint_array(&mut env, path)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_calculateVia<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    start_x_y: JIntArray<'a>,
+    via_x_y: JIntArray<'a>,
+    end_x_y: JIntArray<'a>,
+    reorder_via_points: jboolean,
+) -> JIntArray<'a> {
+    let start = jni_util_extended::from_jint_array_to_vector2_int(&env, start_x_y);
+    let end = jni_util_extended::from_jint_array_to_vector2_int(&env, end_x_y);
+
+    let via = jni_util_extended::from_jint_array_to_vector2_int_vec(&env, via_x_y);
+
+    let astar = get_native_pathfinder(&mut env, &obj).get_planner();
+
+    let via_path =
+        via_points::calculate_path_via(&astar, start, &via, end, reorder_via_points != 0);
+    path_to_jint_array(&mut env, via_path.map(|via_path| via_path.path))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_calculateBatch<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    start_end_x_y: JIntArray<'a>,
+) -> JObjectArray<'a> {
+    let positions = jni_util_extended::from_jint_array_to_vector2_int_vec(&env, start_end_x_y);
+    let (queries, _) = positions.as_chunks::<2>();
+    let queries: Vec<(Vector2<i32>, Vector2<i32>)> =
+        queries.iter().map(|[start, end]| (*start, *end)).collect();
+
+    let astar = Arc::new(get_native_pathfinder(&mut env, &obj).get_planner());
+
+    let paths = BATCH_PLANNER
+        .lock()
+        .expect("Batch planner poisoned")
+        .calculate_paths(astar, &queries);
+    paths_to_jobject_array(
+        &mut env,
+        paths
+            .into_iter()
+            .map(|path| path.unwrap_or_default())
+            .collect(),
+    )
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_calculateAlternatives<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    start_x_y: JIntArray<'a>,
+    end_x_y: JIntArray<'a>,
+    path_count: jint,
+    max_overlap: jfloat,
+    corridor_radius: jint,
+    penalty: jfloat,
+    max_attempts: jint,
+) -> JObjectArray<'a> {
+    let start = jni_util_extended::from_jint_array_to_vector2_int(&env, start_x_y);
+    let end = jni_util_extended::from_jint_array_to_vector2_int(&env, end_x_y);
+
+    let astar = get_native_pathfinder(&mut env, &obj).get_planner();
+
+    let paths = alternatives::calculate_alternative_paths(
+        &astar,
+        start,
+        end,
+        &AlternativePathConfig {
+            path_count: path_count.max(0) as usize,
+            max_overlap: max_overlap as f64,
+            corridor_radius,
+            penalty: penalty as f64,
+            max_attempts: max_attempts.max(0) as usize,
+        },
+    );
+    paths_to_jobject_array(
+        &mut env,
+        paths
+            .into_iter()
+            .map(|alternative| alternative.path)
+            .collect(),
+    )
+}
+
+/// Returns `[first_invalid_index, cost, unknown_share]`, where the index is -1 if the path is
+/// still valid and the share is how many of the path's steps go through unknown cells (0 to 1).
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_validatePath<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    path_x_y: JIntArray<'a>,
+) -> JFloatArray<'a> {
+    let path = jni_util_extended::from_jint_array_to_vector2_int_vec(&env, path_x_y);
+
+    let astar = get_native_pathfinder(&mut env, &obj).get_planner();
+
+    let validation = astar.validate_path(&path);
+    let result = [
+        validation
+            .first_invalid_index
+            .map_or(-1.0, |index| index as f32),
+        validation.cost as f32,
+        validation.unknown_share as f32,
+    ];
+
+    let jfloat_array = env
+        .new_float_array(result.len() as i32)
+        .expect("Failed to create Java float array");
+    env.set_float_array_region(&jfloat_array, 0, &result)
+        .expect("Failed to copy values into Java float array");
+
+    jfloat_array
+}
+
 #[no_mangle]
 pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addHybridObjects<'a>(
     mut env: JNIEnv<'a>,
     obj: JClass<'a>,
     objects: JFloatArray<'a>,
 ) {
-    let astar = get_astar(&mut env, obj);
-    let hybrid_grid = astar.get_grid();
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
 
     let objects = jni_util_extended::jfloatarray_to_vec(&env, objects);
-    for i in (0..objects.len()).step_by(2) {
-        hybrid_grid.add_hybrid_object(&[objects[i], objects[i + 1]]);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        for i in (0..objects.len()).step_by(2) {
+            hybrid_grid.add_hybrid_object(&[objects[i], objects[i + 1]]);
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_upsertHybridObject<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    id: jlong,
+    position_x_y: JFloatArray<'a>,
+    radius: jfloat,
+    object_class: jint,
+    timestamp: jdouble,
+    time_to_live: jdouble,
+) {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    let object = HybridObject {
+        position: jni_util_extended::from_jfloat_array_to_vector2_float(&env, position_x_y),
+        radius,
+        class: ObjectClass::from_index(object_class),
+        timestamp,
+        time_to_live: (time_to_live > 0.0).then_some(time_to_live),
+    };
+
+    native_pathfinder.grid.update(|hybrid_grid| {
+        hybrid_grid.upsert_hybrid_object(id as u64, object);
+    });
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_removeHybridObject<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    id: jlong,
+) -> jboolean {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder
+        .grid
+        .update(|hybrid_grid| hybrid_grid.remove_hybrid_object(id as u64).is_some()) as jboolean
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_setMatchTime<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    time: jdouble,
+) {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        hybrid_grid.set_time(time);
+    });
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_recordOpponentHeat<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    amount: jfloat,
+) -> jint {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder
+        .grid
+        .update(|hybrid_grid| hybrid_grid.record_opponent_heat(amount)) as jint
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addOpponentHeat<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    position_x_y: JFloatArray<'a>,
+    radius: jfloat,
+    amount: jfloat,
+) {
+    let position = jni_util_extended::from_jfloat_array_to_vector2_float(&env, position_x_y);
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        hybrid_grid.add_opponent_heat(position, radius, amount);
+    });
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_resetOpponentHeatmap<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    decay_rate: jfloat,
+) {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder
+        .grid
+        .update(|hybrid_grid| hybrid_grid.reset_opponent_heatmap(decay_rate));
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_setStaticObstacles<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    obstacles: JIntArray<'a>,
+    obstructed: jboolean,
+) {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+
+    let obstacles = jni_util_extended::from_jint_array_to_vector2_int_vec(&env, obstacles);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        hybrid_grid.set_static_obstacles(&obstacles, obstructed != 0);
+    });
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_setUnknownCells<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    cells: JIntArray<'a>,
+    unknown: jboolean,
+) {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+
+    let cells = jni_util_extended::from_jint_array_to_vector2_int_vec(&env, cells);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        hybrid_grid.set_unknown_cells(&cells, unknown != 0);
+    });
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_setUnknownPolicy<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    policy: jint,
+    cost: jfloat,
+) {
+    let policy = match policy {
+        0 => UnknownPolicy::Free,
+        1 => UnknownPolicy::Occupied,
+        _ => UnknownPolicy::Cost(cost as f64),
+    };
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    *native_pathfinder
+        .unknown_policy
+        .lock()
+        .expect("Unknown policy lock poisoned") = policy;
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_setObjectAvoidance<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    object_class: jint,
+    clearance: jfloat,
+    cost: jfloat,
+    hard: jboolean,
+) {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder
+        .object_avoidance
+        .lock()
+        .expect("Object avoidance lock poisoned")
+        .get_or_insert_with(ObjectAvoidance::default)
+        .set_class(
+            ObjectClass::from_index(object_class),
+            ClassAvoidance {
+                clearance,
+                cost,
+                hard: hard != 0,
+            },
+        );
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_setCellCosts<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    cells: JIntArray<'a>,
+    cost: jfloat,
+) {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+
+    let cells = jni_util_extended::from_jint_array_to_vector2_int_vec(&env, cells);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        hybrid_grid.set_cell_costs(&cells, cost);
+    });
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_clearCellCosts<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+) {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder
+        .grid
+        .update(|hybrid_grid| hybrid_grid.clear_cell_costs());
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_clearStaticRegion<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    min_x_y: JIntArray<'a>,
+    max_x_y: JIntArray<'a>,
+) {
+    let min = jni_util_extended::from_jint_array_to_vector2_int(&env, min_x_y);
+    let max = jni_util_extended::from_jint_array_to_vector2_int(&env, max_x_y);
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        hybrid_grid.clear_static_region(min, max);
+    });
+}
+
+fn add_obstacle_shape(
+    native_pathfinder: &NativePathfinder,
+    shape: ObstacleShape,
+    conservative: jboolean,
+) {
+    let coverage = if conservative != 0 {
+        Coverage::Conservative
+    } else {
+        Coverage::Exact
+    };
+
+    native_pathfinder.grid.update(|hybrid_grid| {
+        hybrid_grid.add_obstacle_shape(&shape, coverage);
+    });
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addObstaclePolygon<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    corners_x_y: JFloatArray<'a>,
+    conservative: jboolean,
+) {
+    let corners = jni_util_extended::jfloatarray_to_vec(&env, corners_x_y);
+    let (corners, _) = corners.as_chunks::<2>();
+    let shape = ObstacleShape::Polygon(corners.iter().map(|[x, y]| Vector2::new(*x, *y)).collect());
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    add_obstacle_shape(native_pathfinder, shape, conservative);
+}
+
+fn get_time_windows(env: &JNIEnv, windows: JFloatArray) -> Vec<TimeWindow> {
+    let windows = jni_util_extended::jfloatarray_to_vec(env, windows);
+    let (windows, _) = windows.as_chunks::<2>();
+
+    windows
+        .iter()
+        .map(|[start, end]| TimeWindow::new(*start as f64, *end as f64))
+        .collect()
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addKeepOutPolygon<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    corners_x_y: JFloatArray<'a>,
+    windows: JFloatArray<'a>,
+    conservative: jboolean,
+) -> jlong {
+    let corners = jni_util_extended::jfloatarray_to_vec(&env, corners_x_y);
+    let (corners, _) = corners.as_chunks::<2>();
+    let shape = ObstacleShape::Polygon(corners.iter().map(|[x, y]| Vector2::new(*x, *y)).collect());
+    let windows = get_time_windows(&env, windows);
+    let coverage = if conservative != 0 {
+        Coverage::Conservative
+    } else {
+        Coverage::Exact
+    };
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder
+        .grid
+        .update(|hybrid_grid| hybrid_grid.add_keep_out_shape(&shape, coverage, windows))
+        as jlong
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addKeepOutCells<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    cells: JIntArray<'a>,
+    windows: JFloatArray<'a>,
+) -> jlong {
+    let cells = jni_util_extended::from_jint_array_to_vector2_int_vec(&env, cells);
+    let windows = get_time_windows(&env, windows);
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder
+        .grid
+        .update(|hybrid_grid| hybrid_grid.add_keep_out_zone(KeepOutZone::new(cells, windows)))
+        as jlong
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_removeKeepOutZone<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    id: jlong,
+) -> jboolean {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder
+        .grid
+        .update(|hybrid_grid| hybrid_grid.remove_keep_out_zone(id as u64).is_some()) as jboolean
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_clearKeepOutZones<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+) {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder
+        .grid
+        .update(|hybrid_grid| hybrid_grid.clear_keep_out_zones());
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addZone<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    name: JString<'a>,
+    corners_x_y: JFloatArray<'a>,
+    approach_x_y_heading: JFloatArray<'a>,
+) {
+    let name: String = env
+        .get_string(&name)
+        .expect("Failed to read zone name")
+        .into();
+    let corners = jni_util_extended::jfloatarray_to_vec(&env, corners_x_y);
+    let (corners, _) = corners.as_chunks::<2>();
+    let approach_poses = jni_util_extended::jfloatarray_to_vec(&env, approach_x_y_heading);
+    let (approach_poses, _) = approach_poses.as_chunks::<3>();
+
+    let zone = SemanticZone::new(
+        name,
+        corners.iter().map(|[x, y]| Vector2::new(*x, *y)).collect(),
+    )
+    .with_approach_poses(
+        approach_poses
+            .iter()
+            .map(|[x, y, heading]| ApproachPose::new(Vector2::new(*x, *y), *heading))
+            .collect(),
+    );
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        hybrid_grid.set_semantic_zone(zone);
+    });
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_removeZone<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    name: JString<'a>,
+) -> jboolean {
+    let name: String = env
+        .get_string(&name)
+        .expect("Failed to read zone name")
+        .into();
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder
+        .grid
+        .update(|hybrid_grid| hybrid_grid.remove_semantic_zone(&name).is_some()) as jboolean
+}
+
+/// Replaces the named zones with the ones in a map file, returning how many were loaded or -1 if
+/// the file can't be used.
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_loadZones<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    file_name: JString<'a>,
+) -> jint {
+    let file_name: String = env
+        .get_string(&file_name)
+        .expect("Failed to read file name")
+        .into();
+    let Ok(map) = FieldMap::load(file_name) else {
+        return -1;
+    };
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        hybrid_grid.clear_semantic_zones();
+        for zone in &map.zones {
+            hybrid_grid.set_semantic_zone(zone.into());
+        }
+    });
+
+    map.zones.len() as jint
+}
+
+/// Replaces the grid with a built-in field at the current resolution, returning false if there is
+/// no preset with that name.
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_loadPresetGrid<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    name: JString<'a>,
+) -> jboolean {
+    let name: String = env
+        .get_string(&name)
+        .expect("Failed to read preset name")
+        .into();
+    let Some(preset) = FieldPreset::from_name(&name) else {
+        return false as jboolean;
+    };
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        match preset.build_grid(hybrid_grid.get_square_size_meters()) {
+            Ok(grid) => {
+                *hybrid_grid = grid;
+                true
+            }
+            Err(_) => false,
+        }
+    }) as jboolean
+}
+
+/// Returns `[size_x, size_y, center_x, center_y]` of the current grid in cells.
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_getGridLayout<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+) -> JIntArray<'a> {
+    let snapshot = get_native_pathfinder(&mut env, &obj).grid.snapshot();
+    let grid = snapshot.get_grid();
+
+    path_to_jint_array(&mut env, Some(vec![grid.get_size(), grid.get_center()]))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_getStaticObstacles<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+) -> JIntArray<'a> {
+    let snapshot = get_native_pathfinder(&mut env, &obj).grid.snapshot();
+    let obstacles = snapshot
+        .get_grid()
+        .get_static_obstacles()
+        .iter()
+        .copied()
+        .collect();
+
+    path_to_jint_array(&mut env, Some(obstacles))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_getZonesAt<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    position_x_y: JFloatArray<'a>,
+) -> JObjectArray<'a> {
+    let position = jni_util_extended::from_jfloat_array_to_vector2_float(&env, position_x_y);
+
+    let snapshot = get_native_pathfinder(&mut env, &obj).grid.snapshot();
+    let zones = snapshot.get_grid().get_semantic_zones_at(position);
+
+    let object_array = env
+        .new_object_array(zones.len() as i32, "java/lang/String", JObject::null())
+        .expect("Failed to create Java String array");
+    for (i, zone) in zones.iter().enumerate() {
+        let name = env
+            .new_string(&zone.name)
+            .expect("Failed to create Java string");
+        env.set_object_array_element(&object_array, i as i32, name)
+            .expect("Failed to copy name into Java String array");
     }
+
+    object_array
+}
+
+/// Returns the name of the zone closest to the position, or null if there are no zones.
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_getNearestZone<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    position_x_y: JFloatArray<'a>,
+) -> JString<'a> {
+    let position = jni_util_extended::from_jfloat_array_to_vector2_float(&env, position_x_y);
+
+    let snapshot = get_native_pathfinder(&mut env, &obj).grid.snapshot();
+    match snapshot.get_grid().get_nearest_semantic_zone(position) {
+        Some((zone, _)) => env
+            .new_string(&zone.name)
+            .expect("Failed to create Java string"),
+        None => JObject::null().into(),
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_calculateToZone<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    start_x_y: JIntArray<'a>,
+    name: JString<'a>,
+) -> JIntArray<'a> {
+    let start = jni_util_extended::from_jint_array_to_vector2_int(&env, start_x_y);
+    let name: String = env
+        .get_string(&name)
+        .expect("Failed to read zone name")
+        .into();
+
+    let astar = get_native_pathfinder(&mut env, &obj).get_planner();
+    // The zone is looked up in the grid the planner searches, not a newer one
+    let grid = astar.get_grid_snapshot();
+
+    let zone_path = grid
+        .get_semantic_zone(&name)
+        .and_then(|zone| zones::calculate_path_to_zone(&astar, &grid, start, zone));
+    path_to_jint_array(&mut env, zone_path.map(|zone_path| zone_path.path))
+}
+
+/// Replaces the grid with what the other alliance sees, see `AllianceTransform::flip_grid`.
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_flipAlliance<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    symmetry: jint,
+) {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        let transform = AllianceTransform::new(hybrid_grid, FieldSymmetry::from_index(symmetry));
+        *hybrid_grid = transform.flip_grid(hybrid_grid);
+    });
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_flipPath<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    path_x_y: JIntArray<'a>,
+    symmetry: jint,
+) -> JIntArray<'a> {
+    let path = jni_util_extended::from_jint_array_to_vector2_int_vec(&env, path_x_y);
+
+    let snapshot = get_native_pathfinder(&mut env, &obj).grid.snapshot();
+    let transform =
+        AllianceTransform::new(snapshot.get_grid(), FieldSymmetry::from_index(symmetry));
+
+    path_to_jint_array(&mut env, Some(transform.flip_path(&path)))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_setTravelSpeed<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    cells_per_second: jfloat,
+) {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    *native_pathfinder
+        .travel_speed
+        .lock()
+        .expect("Travel speed lock poisoned") =
+        (cells_per_second > 0.0).then_some(cells_per_second as f64);
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addObstacleCircle<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    center: JFloatArray<'a>,
+    radius: jfloat,
+    conservative: jboolean,
+) {
+    let shape = ObstacleShape::Circle {
+        center: jni_util_extended::from_jfloat_array_to_vector2_float(&env, center),
+        radius,
+    };
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    add_obstacle_shape(native_pathfinder, shape, conservative);
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addObstacleRectangle<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    center: JFloatArray<'a>,
+    size: JFloatArray<'a>,
+    rotation: jfloat,
+    conservative: jboolean,
+) {
+    let shape = ObstacleShape::Rectangle {
+        center: jni_util_extended::from_jfloat_array_to_vector2_float(&env, center),
+        size: jni_util_extended::from_jfloat_array_to_vector2_float(&env, size),
+        rotation,
+    };
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    add_obstacle_shape(native_pathfinder, shape, conservative);
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addObstacleSegment<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    start: JFloatArray<'a>,
+    end: JFloatArray<'a>,
+    thickness: jfloat,
+    conservative: jboolean,
+) {
+    let shape = ObstacleShape::Segment {
+        start: jni_util_extended::from_jfloat_array_to_vector2_float(&env, start),
+        end: jni_util_extended::from_jfloat_array_to_vector2_float(&env, end),
+        thickness,
+    };
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    add_obstacle_shape(native_pathfinder, shape, conservative);
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addObservations<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    sensor_x_y: JFloatArray<'a>,
+    ends_x_y: JFloatArray<'a>,
+    is_hit: jboolean,
+) {
+    let sensor = jni_util_extended::from_jfloat_array_to_vector2_float(&env, sensor_x_y);
+    let ends = jni_util_extended::jfloatarray_to_vec(&env, ends_x_y);
+    let (ends, _) = ends.as_chunks::<2>();
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        for [x, y] in ends {
+            hybrid_grid.integrate_observation(sensor, Vector2::new(*x, *y), is_hit != 0);
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_clearObservations<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+) {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        let config = hybrid_grid.get_observations().get_config().clone();
+        hybrid_grid.reset_observations(config);
+    });
 }
 
 #[no_mangle]
@@ -84,9 +932,10 @@ pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_clearHybri
     mut env: JNIEnv<'a>,
     obj: JClass<'a>,
 ) {
-    let astar = get_astar(&mut env, obj);
-    let hybrid_grid = astar.get_grid();
-    hybrid_grid.clear_hybrid_objects();
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder
+        .grid
+        .update(|hybrid_grid| hybrid_grid.clear_hybrid_objects());
 }
 
 #[no_mangle]
@@ -94,9 +943,10 @@ pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_clearUncer
     mut env: JNIEnv<'a>,
     obj: JClass<'a>,
 ) {
-    let astar = get_astar(&mut env, obj);
-    let hybrid_grid = astar.get_grid();
-    hybrid_grid.clear_uncertenty_fields();
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder
+        .grid
+        .update(|hybrid_grid| hybrid_grid.clear_uncertenty_fields());
 }
 
 #[no_mangle]
@@ -106,12 +956,160 @@ pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addUncerte
     center: JFloatArray<'a>,
     radius: jfloat,
     intensity: jfloat,
-) {
-    let astar = get_astar(&mut env, obj);
-    let hybrid_grid = astar.get_grid();
+) -> jlong {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
     let field_center: Vector2<f32> =
         jni_util_extended::from_jfloat_array_to_vector2_float(&env, center);
-    hybrid_grid.add_uncertenty_field(field_center, radius, intensity);
+    native_pathfinder
+        .grid
+        .update(|hybrid_grid| hybrid_grid.add_uncertenty_field(field_center, radius, intensity))
+        as jlong
+}
+
+fn push_uncertenty_field(
+    native_pathfinder: &NativePathfinder,
+    center: Vector2<f32>,
+    shape: FieldShape,
+    intensity: jfloat,
+    falloff: jint,
+) -> jlong {
+    let mut field = UncertentyField::new(center, shape, intensity);
+    field.falloff = Falloff::from_index(falloff);
+
+    native_pathfinder.grid.update(|hybrid_grid| {
+        field.timestamp = hybrid_grid.get_time();
+        hybrid_grid.push_uncertenty_field(field)
+    }) as jlong
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addUncertentyEllipse<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    center: JFloatArray<'a>,
+    radii: JFloatArray<'a>,
+    rotation: jfloat,
+    intensity: jfloat,
+    falloff: jint,
+) -> jlong {
+    let center = jni_util_extended::from_jfloat_array_to_vector2_float(&env, center);
+    let shape = FieldShape::Ellipse {
+        radii: jni_util_extended::from_jfloat_array_to_vector2_float(&env, radii),
+        rotation,
+    };
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    push_uncertenty_field(native_pathfinder, center, shape, intensity, falloff)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addUncertentyRectangle<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    center: JFloatArray<'a>,
+    size: JFloatArray<'a>,
+    rotation: jfloat,
+    intensity: jfloat,
+    falloff: jint,
+) -> jlong {
+    let center = jni_util_extended::from_jfloat_array_to_vector2_float(&env, center);
+    let shape = FieldShape::Rectangle {
+        size: jni_util_extended::from_jfloat_array_to_vector2_float(&env, size),
+        rotation,
+    };
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    push_uncertenty_field(native_pathfinder, center, shape, intensity, falloff)
+}
+
+/// Adds a Gaussian field from a {xx, xy, yy} covariance, returning its ID or -1 if the
+/// covariance has fewer than three values.
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_addUncertentyGaussian<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    center: JFloatArray<'a>,
+    covariance: JFloatArray<'a>,
+    intensity: jfloat,
+    falloff: jint,
+) -> jlong {
+    let center = jni_util_extended::from_jfloat_array_to_vector2_float(&env, center);
+    let covariance = jni_util_extended::jfloatarray_to_vec(&env, covariance);
+    if covariance.len() < 3 {
+        return -1;
+    }
+    let shape = FieldShape::Gaussian {
+        covariance: Matrix2::new(covariance[0], covariance[1], covariance[1], covariance[2]),
+    };
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    push_uncertenty_field(native_pathfinder, center, shape, intensity, falloff)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_updateUncertentyField<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    id: jlong,
+    center: JFloatArray<'a>,
+    scale: jfloat,
+    intensity: jfloat,
+) -> jboolean {
+    let center = jni_util_extended::from_jfloat_array_to_vector2_float(&env, center);
+
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        let Some(field) = hybrid_grid.get_uncertenty_def(id as u64) else {
+            return false;
+        };
+
+        let field = UncertentyField {
+            center,
+            shape: field.shape.scaled(scale),
+            intensity,
+            timestamp: hybrid_grid.get_time(),
+            ..field.clone()
+        };
+        hybrid_grid.update_uncertenty_field(id as u64, field)
+    }) as jboolean
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_setUncertentyFieldDecay<
+    'a,
+>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    id: jlong,
+    decay_rate: jfloat,
+    time_to_live: jdouble,
+) -> jboolean {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder.grid.update(|hybrid_grid| {
+        let Some(field) = hybrid_grid.get_uncertenty_def(id as u64) else {
+            return false;
+        };
+
+        let field = UncertentyField {
+            decay_rate,
+            time_to_live: (time_to_live > 0.0).then_some(time_to_live),
+            ..field.clone()
+        };
+        hybrid_grid.update_uncertenty_field(id as u64, field)
+    }) as jboolean
+}
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_removeUncertentyField<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+    id: jlong,
+) -> jboolean {
+    let native_pathfinder = get_native_pathfinder(&mut env, &obj);
+    native_pathfinder
+        .grid
+        .update(|hybrid_grid| hybrid_grid.remove_uncertenty_field(id as u64).is_some())
+        as jboolean
 }
 
 #[no_mangle]
@@ -162,19 +1160,41 @@ pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_initialize
         jni_util_extended::from_jfloat_array_to_vector2_float(&env, finder_relative_w_h);
     let distance = robot_dimensions.magnitude();
 
-    let astar = AStar::build(
-        hybrid_grid,
-        node_pick_style,
-        NodeRadiusSearch {
+    let native_pathfinder = NativePathfinder {
+        grid: SharedGrid::new(hybrid_grid),
+        pick_style: node_pick_style,
+        node_radius_search_config: NodeRadiusSearch {
             node_radius_search_radius_squared: distance * distance,
             do_absolute_discard: do_absolute_discard != 0,
             avg_distance_min_discard_threshold: avg_distance_min_discard_threshold as f32,
             avg_distance_cost: avg_distance_cost as f32,
         },
-    );
+        unknown_policy: Mutex::new(UnknownPolicy::Free),
+        object_avoidance: Mutex::new(None),
+        travel_speed: Mutex::new(None),
+    };
 
-    let boxed_astar = Box::new(astar);
-    let ptr = Box::into_raw(boxed_astar);
+    let boxed_native_pathfinder = Box::new(native_pathfinder);
+    let ptr = Box::into_raw(boxed_native_pathfinder);
 
     ptr as jlong
 }
+
+#[no_mangle]
+pub extern "system" fn Java_org_pwrup_napoleon_bridge_AStarPathfinder_cleanup<'a>(
+    mut env: JNIEnv<'a>,
+    obj: JClass<'a>,
+) {
+    let ptr = env
+        .get_field(&obj, "nativePtr", "J")
+        .expect("Field not found")
+        .j()
+        .unwrap();
+    if ptr == 0 {
+        return;
+    }
+
+    env.set_field(&obj, "nativePtr", "J", JValue::Long(0))
+        .expect("Failed to reset nativePtr");
+    drop(unsafe { Box::from_raw(ptr as *mut NativePathfinder) });
+}
@@ -3,8 +3,15 @@ use nalgebra::{Vector2, Vector3};
 use crate::hybrid_grid::{GenericDynamicObject, HybridGrid};
 
 pub mod a_star;
+pub mod alternatives;
+pub mod batch;
+pub mod costmap;
+pub mod replanner;
 pub mod rrt_star;
+pub mod via_points;
+pub mod zones;
 
+#[derive(Clone)]
 pub struct NodeRadiusSearch {
     pub node_radius_search_radius_squared: f32,
 
@@ -20,8 +27,56 @@ pub trait Pathfinding {
     fn calculate_path(&self, start: Vector2<i32>, end: Vector2<i32>) -> Option<Vec<Vector2<i32>>>;
 }
 
+/// The result of re-evaluating an existing path against the current grid.
+pub struct PathValidation {
+    /// The index of the first position that can no longer be entered, or `None` if the whole path
+    /// is still traversable
+    pub first_invalid_index: Option<usize>,
+    /// The cost of the path up to (not including) the first invalid position
+    pub cost: f64,
+    /// The share of the path's steps that run through unknown cells, from 0 to 1
+    pub unknown_share: f64,
+}
+
+impl PathValidation {
+    pub fn is_valid(&self) -> bool {
+        self.first_invalid_index.is_none()
+    }
+}
+
+/// Pathfinders that can price an existing path under the same cost model they search with.
+pub trait PathCost {
+    /// Re-evaluates `path` against the current state of the grid. The first position is where the
+    /// path starts and is neither checked nor charged.
+    fn validate_path(&self, path: &[Vector2<i32>]) -> PathValidation;
+
+    /// # Returns
+    /// The total cost of following `path`, or `None` if the path is not traversable.
+    fn path_cost(&self, path: &[Vector2<i32>]) -> Option<f64> {
+        let validation = self.validate_path(path);
+        if validation.is_valid() {
+            Some(validation.cost)
+        } else {
+            None
+        }
+    }
+}
+
+/// Pathfinders that can take an extra cost per position into account for a single search, on top
+/// of their own cost model. The overlay is charged along the path like travelled distance, so a
+/// search can be steered away from whole regions.
+pub trait OverlayPathfinding: Pathfinding {
+    fn calculate_path_with_overlay(
+        &self,
+        start: Vector2<i32>,
+        end: Vector2<i32>,
+        overlay: &dyn Fn(Vector2<i32>) -> f64,
+    ) -> Option<Vec<Vector2<i32>>>;
+}
+
 /// Pathfinders that calculate the total cost of a given node time relative rather than absolute position h, g cost.
 /// This might be slower, but it allows for more accurate pathfinding with a robot.
+/// Keep-out zones are enforced by `calculate_path_timed_keeping_out`, which callers should prefer.
 pub trait TimedPathfinding<D: GenericDynamicObject> {
     fn calculate_path_timed(
         &self,
@@ -29,4 +84,101 @@ pub trait TimedPathfinding<D: GenericDynamicObject> {
         end: Vector3<f64>,
         consider_n_intermediate_steps: usize,
     ) -> Vec<Vector3<f64>>;
+
+    /// # Returns
+    /// The timed path from `start` to `end`, or `None` if it enters a keep-out zone of `grid`
+    /// while the zone is active.
+    fn calculate_path_timed_keeping_out(
+        &self,
+        grid: &HybridGrid,
+        start: Vector3<f64>,
+        end: Vector3<f64>,
+        consider_n_intermediate_steps: usize,
+    ) -> Option<Vec<Vector3<f64>>> {
+        let path = self.calculate_path_timed(start, end, consider_n_intermediate_steps);
+        find_keep_out_violation(grid, &path)
+            .is_none()
+            .then_some(path)
+    }
+}
+
+/// # Returns
+/// The index of the first waypoint that is inside an active keep-out zone, or `None` if the whole
+/// path keeps out.
+/// # Arguments
+/// * `grid` - The grid holding the zones
+/// * `path` - Waypoints as (x, y, time), in grid cells and seconds after the grid's match time
+pub fn find_keep_out_violation(grid: &HybridGrid, path: &[Vector3<f64>]) -> Option<usize> {
+    path.iter().position(|waypoint| {
+        let cell = Vector2::new(waypoint.x.round() as i32, waypoint.y.round() as i32);
+        grid.is_kept_out(cell, grid.get_time() + waypoint.z)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_grid::keep_out::{KeepOutZone, TimeWindow};
+    use crate::time_structures::TimedPath;
+
+    #[test]
+    fn test_find_keep_out_violation() {
+        let mut grid = HybridGrid::new(10, 10, 1.0, vec![], 5, 5);
+        grid.add_keep_out_zone(KeepOutZone::new(
+            [Vector2::new(3, 3)],
+            vec![TimeWindow::new(130.0, 150.0)],
+        ));
+        grid.set_time(125.0);
+
+        let early = [Vector3::new(2.0, 2.0, 0.0), Vector3::new(3.1, 2.9, 1.0)];
+        assert_eq!(find_keep_out_violation(&grid, &early), None);
+
+        let late = [Vector3::new(2.0, 2.0, 0.0), Vector3::new(3.1, 2.9, 6.0)];
+        assert_eq!(find_keep_out_violation(&grid, &late), Some(1));
+    }
+
+    #[test]
+    fn test_timed_paths_keep_out() {
+        struct Straight;
+
+        impl TimedPathfinding<Box<dyn GenericDynamicObject>> for Straight {
+            fn calculate_path_timed(
+                &self,
+                start: Vector3<f64>,
+                end: Vector3<f64>,
+                consider_n_intermediate_steps: usize,
+            ) -> Vec<Vector3<f64>> {
+                let steps = consider_n_intermediate_steps + 1;
+                (0..=steps)
+                    .map(|i| start.lerp(&end, i as f64 / steps as f64))
+                    .collect()
+            }
+        }
+
+        let mut grid = HybridGrid::new(10, 10, 1.0, vec![], 5, 5);
+        grid.add_keep_out_zone(KeepOutZone::new(
+            [Vector2::new(4, 5)],
+            vec![TimeWindow::new(5.0, 7.0)],
+        ));
+
+        let start = Vector3::new(0.0, 5.0, 0.0);
+        assert!(Straight
+            .calculate_path_timed_keeping_out(&grid, start, Vector3::new(8.0, 5.0, 12.0), 7)
+            .is_none());
+        assert!(Straight
+            .calculate_path_timed_keeping_out(&grid, start, Vector3::new(8.0, 5.0, 24.0), 7)
+            .is_some());
+
+        // 3 seconds per key feature, so the middle one is reached after 6
+        let mut trajectory = TimedPath::new(1.0, 1.0);
+        for x in [0.0, 2.0, 4.0, 6.0, 8.0] {
+            trajectory.add_key_feature(Vector2::new(x, 5.0), Vector2::new(1.0, 0.0));
+        }
+        trajectory.compute_spline();
+        let time = trajectory.find_keep_out_violation(&grid, 0.1).unwrap();
+        assert!((5.0..7.0).contains(&time));
+
+        grid.set_time(10.0);
+        assert_eq!(trajectory.find_keep_out_violation(&grid, 0.1), None);
+    }
 }
@@ -1,47 +1,53 @@
-use super::{NodeRadiusSearch, Pathfinding};
+use super::costmap::{CellQuery, CombineRule, Costmap, UnknownLayer, UnknownPolicy, UNKNOWN_LAYER};
+use super::{NodeRadiusSearch, OverlayPathfinding, PathCost, PathValidation, Pathfinding};
 use crate::hybrid_grid::HybridGrid;
-use core::f64;
-use kiddo::NearestNeighbour;
-use nalgebra::{distance, Vector2};
+use nalgebra::Vector2;
 use node::{Node, NodePickStyle};
 use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::sync::Arc;
 
 pub mod node;
 
-fn average_distance(nodes: &Vec<f32>) -> f32 {
-    if nodes.len() == 0 {
-        return 0.0;
-    }
-
-    nodes.iter().sum::<f32>() / nodes.len() as f32
-}
-
-fn get_distance_squared(node: &Vector2<i32>, other: &Vector2<i32>) -> f32 {
-    return (node.x as f32 - other.x as f32).powi(2) + (node.y as f32 - other.y as f32).powi(2);
-}
-
+#[derive(Clone)]
 pub struct AStar {
-    grid: HybridGrid,
+    grid: Arc<HybridGrid>,
     pick_style: NodePickStyle,
-    node_radius_search_config: NodeRadiusSearch,
+    costmap: Costmap,
+    /// Cells per second, used to estimate when each cell is reached. Without it every cell is
+    /// checked at the grid's current match time.
+    travel_speed: Option<f64>,
+    /// Also charged into the path cost, so that avoiding unknown cells adds up along a path
+    unknown_policy: UnknownPolicy,
 }
 
 impl Pathfinding for AStar {
     fn new(hybrid_grid: HybridGrid) -> Self {
         Self {
-            grid: hybrid_grid,
+            grid: Arc::new(hybrid_grid),
             pick_style: NodePickStyle::ALL,
-            node_radius_search_config: NodeRadiusSearch {
+            costmap: Costmap::from_node_radius_search(NodeRadiusSearch {
                 node_radius_search_radius_squared: 1.0,
                 do_absolute_discard: false,
                 avg_distance_min_discard_threshold: 1.0,
                 avg_distance_cost: 1.0,
-            },
+            }),
+            travel_speed: None,
+            unknown_policy: UnknownPolicy::Free,
         }
     }
 
     fn calculate_path(&self, start: Vector2<i32>, end: Vector2<i32>) -> Option<Vec<Vector2<i32>>> {
+        self.calculate_path_with_overlay(start, end, &|_| 0.0)
+    }
+}
+
+impl OverlayPathfinding for AStar {
+    fn calculate_path_with_overlay(
+        &self,
+        start: Vector2<i32>,
+        end: Vector2<i32>,
+        overlay: &dyn Fn(Vector2<i32>) -> f64,
+    ) -> Option<Vec<Vector2<i32>>> {
         let end_node = Node::new(end, None);
         let mut open_set = BinaryHeap::new();
         let mut closed_set = HashSet::new();
@@ -67,66 +73,40 @@ impl Pathfinding for AStar {
             for mut neighbor in current.get_positions_around(&self.pick_style) {
                 if closed_set.contains(&neighbor.get_position())
                     || self.grid.is_outside_grid(neighbor.get_position())
-                    || self.grid.is_obstructed(neighbor.get_position())
                 {
                     continue;
                 }
 
-                let static_nodes_in_radius = self.grid.get_all_obstructions_in_radius(
-                    neighbor.get_position(),
-                    self.node_radius_search_config
-                        .node_radius_search_radius_squared as i32,
-                );
-                let hybrid_nodes_in_radius = self.grid.get_nearest(
-                    neighbor.get_position(),
-                    self.node_radius_search_config
-                        .node_radius_search_radius_squared as f32,
+                let arrival_ms = current.get_time_ms_since_initial()
+                    + self.get_travel_time_ms(current.distance_to(&neighbor));
+                neighbor.set_time_ms_since_initial(arrival_ms);
+
+                let extra_cost = self.costmap.get_cost(
+                    &self.grid,
+                    &CellQuery {
+                        position: neighbor.get_position(),
+                        is_goal: neighbor.get_position() == end_node.get_position(),
+                        time: self.grid.get_time() + arrival_ms / 1000.0,
+                    },
                 );
-                let mut all_distances = static_nodes_in_radius
-                    .iter()
-                    .map(|node| get_distance_squared(&neighbor.get_position(), &node))
-                    .collect::<Vec<f32>>();
-                all_distances.extend(hybrid_nodes_in_radius.iter().map(|node| node.distance));
-
-                let avg_distance = average_distance(&all_distances);
-                if neighbor.get_position() != end_node.get_position() {
-                    if self.node_radius_search_config.do_absolute_discard
-                        && avg_distance != 0.0
-                        && avg_distance
-                            <= self
-                                .node_radius_search_config
-                                .avg_distance_min_discard_threshold
-                    {
-                        continue;
-                    }
+                if extra_cost.is_infinite() {
+                    continue;
                 }
 
                 let g_score = *g_scores.get(&position).unwrap_or(&f64::MIN);
                 let neighbor_position = neighbor.get_position();
-                let tentative_g_cost = g_score + current.distance_to(&neighbor);
+                let tentative_g_cost = g_score
+                    + self.get_step_cost(current.distance_to(&neighbor), neighbor_position)
+                    + self.get_unknown_cost(neighbor_position)
+                    + overlay(neighbor_position);
                 let neighbor_g_cost = g_scores
                     .get(&neighbor_position)
                     .copied()
                     .unwrap_or(f64::INFINITY);
 
                 if tentative_g_cost < neighbor_g_cost {
-                    let mut extra_cost =
-                        if let Some((field, distance)) = self.grid.get_uncertenty_field(
-                            Vector2::new(neighbor_position.x as f32, neighbor_position.y as f32),
-                        ) {
-                            self.grid.uncertenty_field_cost_ramping(
-                                distance,
-                                field.radius,
-                                field.intensity,
-                            )
-                        } else {
-                            0.0
-                        };
-                    extra_cost += avg_distance * self.node_radius_search_config.avg_distance_cost;
-
-                    neighbor.set_cost(
-                        tentative_g_cost + end_node.distance_to(&neighbor) + extra_cost as f64,
-                    );
+                    neighbor
+                        .set_cost(tentative_g_cost + end_node.distance_to(&neighbor) + extra_cost);
 
                     g_scores.insert(neighbor.get_position(), tentative_g_cost);
                     open_set.push(neighbor);
@@ -138,7 +118,71 @@ impl Pathfinding for AStar {
     }
 }
 
+impl PathCost for AStar {
+    fn validate_path(&self, path: &[Vector2<i32>]) -> PathValidation {
+        let offsets = self.pick_style.get_offsets(1);
+        let mut cost = 0.0;
+        let mut arrival_ms = 0.0;
+
+        for i in 1..path.len() {
+            let step = path[i] - path[i - 1];
+            let distance = (step.x as f64).hypot(step.y as f64);
+            arrival_ms += self.get_travel_time_ms(distance);
+            let extra_cost = if !offsets.iter().any(|offset| offset.xy() == step)
+                || self.grid.is_outside_grid(path[i])
+            {
+                f64::INFINITY
+            } else {
+                self.costmap.get_cost(
+                    &self.grid,
+                    &CellQuery {
+                        position: path[i],
+                        is_goal: i == path.len() - 1,
+                        time: self.grid.get_time() + arrival_ms / 1000.0,
+                    },
+                )
+            };
+
+            if extra_cost.is_infinite() {
+                return PathValidation {
+                    first_invalid_index: Some(i),
+                    cost,
+                    unknown_share: self.grid.get_unknown_share(path),
+                };
+            }
+
+            cost += self.get_step_cost(distance, path[i]) + extra_cost;
+        }
+
+        PathValidation {
+            first_invalid_index: None,
+            cost,
+            unknown_share: self.grid.get_unknown_share(path),
+        }
+    }
+}
+
 impl AStar {
+    /// # Returns
+    /// The cost of travelling `distance` into `position`, scaled up by the cell's traversal cost.
+    fn get_step_cost(&self, distance: f64, position: Vector2<i32>) -> f64 {
+        distance * (1.0 + self.grid.get_cell_cost(position) as f64)
+    }
+
+    /// # Returns
+    /// The cost the unknown policy charges for entering `position`, 0 unless it is a cost.
+    fn get_unknown_cost(&self, position: Vector2<i32>) -> f64 {
+        match self.unknown_policy {
+            UnknownPolicy::Cost(cost) if self.grid.is_unknown(position) => cost,
+            _ => 0.0,
+        }
+    }
+
+    fn get_travel_time_ms(&self, distance: f64) -> f64 {
+        self.travel_speed
+            .map_or(0.0, |speed| distance / speed * 1000.0)
+    }
+
     fn reconstruct_path(&self, head_node: Node) -> Vec<Vector2<i32>> {
         let mut output: Vec<Vector2<i32>> = Vec::new();
         output.push(head_node.get_position());
@@ -154,19 +198,75 @@ impl AStar {
         output
     }
 
+    /// # Returns
+    /// The grid for editing. If a snapshot of it is still shared with another planner, the grid is
+    /// copied first so that planner keeps seeing the old state.
     pub fn get_grid(&mut self) -> &mut HybridGrid {
-        return &mut self.grid;
+        Arc::make_mut(&mut self.grid)
     }
 
+    pub fn get_grid_snapshot(&self) -> Arc<HybridGrid> {
+        self.grid.clone()
+    }
+
+    /// # Returns
+    /// A planner with the same configuration that searches `grid` instead.
+    pub fn with_grid(&self, grid: Arc<HybridGrid>) -> Self {
+        Self {
+            grid,
+            ..self.clone()
+        }
+    }
+
+    /// Sets how the search treats unknown cells.
+    pub fn set_unknown_policy(&mut self, policy: UnknownPolicy) {
+        self.unknown_policy = policy;
+        self.costmap
+            .push_layer(UNKNOWN_LAYER, UnknownLayer { policy }, CombineRule::Sum);
+    }
+
+    /// Sets the speed, in cells per second, used to estimate when each cell is reached, so that
+    /// time-windowed keep-out zones are checked at the time the robot would actually be there.
+    /// `None` checks every cell at the grid's current match time.
+    pub fn set_travel_speed(&mut self, travel_speed: Option<f64>) {
+        self.travel_speed = travel_speed;
+    }
+
+    pub fn get_costmap(&self) -> &Costmap {
+        &self.costmap
+    }
+
+    /// # Returns
+    /// The costmap for adding, removing or reordering layers.
+    pub fn get_costmap_mut(&mut self) -> &mut Costmap {
+        &mut self.costmap
+    }
+
+    /// # Returns
+    /// A planner using the built-in costmap configured by `node_radius_search_config`.
     pub fn build(
-        hybrid_grid: HybridGrid,
+        hybrid_grid: impl Into<Arc<HybridGrid>>,
         pick_style: NodePickStyle,
         node_radius_search_config: NodeRadiusSearch,
+    ) -> Self {
+        Self::build_with_costmap(
+            hybrid_grid,
+            pick_style,
+            Costmap::from_node_radius_search(node_radius_search_config),
+        )
+    }
+
+    pub fn build_with_costmap(
+        hybrid_grid: impl Into<Arc<HybridGrid>>,
+        pick_style: NodePickStyle,
+        costmap: Costmap,
     ) -> Self {
         Self {
-            grid: hybrid_grid,
+            grid: hybrid_grid.into(),
             pick_style,
-            node_radius_search_config,
+            costmap,
+            travel_speed: None,
+            unknown_policy: UnknownPolicy::Free,
         }
     }
 }
@@ -174,6 +274,9 @@ impl AStar {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hybrid_grid::hybrid_objects::{HybridObject, ObjectClass};
+    use crate::hybrid_grid::keep_out::{KeepOutZone, TimeWindow};
+    use crate::pathfinding::costmap::{ClassAvoidance, CostLayer, ObjectAvoidance, KEEP_OUT_LAYER};
     use nalgebra::Vector2;
 
     fn create_empty_grid() -> HybridGrid {
@@ -391,4 +494,240 @@ mod tests {
         // Path should take a longer route to avoid the high-intensity area
         assert!(path.len() > 5); // Should take a longer path than direct diagonal
     }
+
+    #[test]
+    fn test_path_cost() {
+        let grid = create_blocked_grid();
+        let astar = AStar::new(grid);
+
+        let path = astar
+            .calculate_path(Vector2::new(0, 0), Vector2::new(0, 3))
+            .unwrap();
+        assert_eq!(astar.path_cost(&path).map(|cost| cost >= 3.0), Some(true));
+
+        let through_obstacle = [Vector2::new(1, 2), Vector2::new(2, 2), Vector2::new(3, 3)];
+        assert!(astar.path_cost(&through_obstacle).is_none());
+
+        let skipping_cells = [Vector2::new(0, 0), Vector2::new(0, 2)];
+        assert!(astar.path_cost(&skipping_cells).is_none());
+    }
+
+    #[test]
+    fn test_validate_path_after_grid_change() {
+        let mut astar = AStar::new(create_empty_grid());
+        let path = astar
+            .calculate_path(Vector2::new(0, 0), Vector2::new(0, 6))
+            .unwrap();
+
+        let validation = astar.validate_path(&path);
+        assert!(validation.is_valid());
+        let original_cost = validation.cost;
+
+        astar
+            .get_grid()
+            .add_uncertenty_field(Vector2::new(0.0, 3.0), 2.0, 10.0);
+        let validation = astar.validate_path(&path);
+        assert!(validation.is_valid());
+        assert!(validation.cost > original_cost);
+
+        astar.get_grid().push_static_obstacle(path[4]);
+        let validation = astar.validate_path(&path);
+        assert_eq!(validation.first_invalid_index, Some(4));
+        assert!(astar.path_cost(&path).is_none());
+    }
+
+    #[test]
+    fn test_grid_edits_do_not_leak_into_shared_snapshots() {
+        let mut astar = AStar::new(create_empty_grid());
+        let other = astar.with_grid(astar.get_grid_snapshot());
+
+        astar.get_grid().push_static_obstacle(Vector2::new(1, 1));
+
+        assert!(astar.get_grid_snapshot().is_obstructed(Vector2::new(1, 1)));
+        assert!(!other.get_grid_snapshot().is_obstructed(Vector2::new(1, 1)));
+    }
+
+    #[test]
+    fn test_cell_costs() {
+        let wall: Vec<Vector2<i32>> = (0..6).map(|i| Vector2::new(4, i)).collect();
+        let start = Vector2::new(0, 0);
+        let end = Vector2::new(8, 0);
+
+        let mut astar = AStar::new(create_empty_grid());
+        astar.get_grid().set_cell_costs(&wall, 1.0);
+        let path = astar.calculate_path(start, end).unwrap();
+        assert!(path.iter().any(|position| wall.contains(position)));
+        assert!((astar.path_cost(&path).unwrap() - 9.0).abs() < 1e-9);
+
+        astar.get_grid().set_cell_costs(&wall, 20.0);
+        let path = astar.calculate_path(start, end).unwrap();
+        assert!(!path.iter().any(|position| wall.contains(position)));
+    }
+
+    #[test]
+    fn test_custom_cost_layer() {
+        struct Column;
+
+        impl CostLayer for Column {
+            fn get_cost(&self, _grid: &HybridGrid, query: &CellQuery) -> Option<f64> {
+                (query.position.x == 2 && query.position.y < 8).then_some(f64::INFINITY)
+            }
+        }
+
+        let mut astar = AStar::new(create_empty_grid());
+        astar
+            .get_costmap_mut()
+            .push_layer("column", Column, CombineRule::Max);
+
+        let path = astar
+            .calculate_path(Vector2::new(0, 0), Vector2::new(4, 0))
+            .unwrap();
+        assert!(path
+            .iter()
+            .all(|position| position.x != 2 || position.y >= 8));
+        assert!(astar
+            .path_cost(&[Vector2::new(1, 0), Vector2::new(2, 0)])
+            .is_none());
+    }
+
+    #[test]
+    fn test_unknown_policy() {
+        let mut grid = create_empty_grid();
+        let unknown: Vec<Vector2<i32>> = (0..9).map(|i| Vector2::new(2, i)).collect();
+        grid.set_unknown_cells(&unknown, true);
+        let mut astar = AStar::new(grid);
+        let start = Vector2::new(0, 0);
+        let end = Vector2::new(4, 0);
+
+        let path = astar.calculate_path(start, end).unwrap();
+        let validation = astar.validate_path(&path);
+        assert!(validation.is_valid());
+        assert!(validation.unknown_share > 0.0);
+
+        astar.set_unknown_policy(UnknownPolicy::Occupied);
+        let path = astar.calculate_path(start, end).unwrap();
+        assert!(path.contains(&Vector2::new(2, 9)));
+        assert_eq!(astar.validate_path(&path).unknown_share, 0.0);
+
+        astar.set_unknown_policy(UnknownPolicy::Cost(3.0));
+        let path = astar.calculate_path(start, end).unwrap();
+        assert!(path.iter().any(|position| unknown.contains(position)));
+    }
+
+    #[test]
+    fn test_unknown_cost_adds_up() {
+        let mut grid = create_empty_grid();
+        for x in 3..=5 {
+            for y in 2..=6 {
+                grid.push_static_obstacle(Vector2::new(x, y));
+            }
+        }
+        // The upper detour around the block is shorter but crosses far more unknown cells
+        let mut unknown: Vec<Vector2<i32>> = (2..=6)
+            .flat_map(|x| (7..=9).map(move |y| Vector2::new(x, y)))
+            .collect();
+        unknown.extend([Vector2::new(4, 0), Vector2::new(4, 1)]);
+        grid.set_unknown_cells(&unknown, true);
+
+        let mut astar = AStar::new(grid);
+        astar.set_unknown_policy(UnknownPolicy::Cost(3.0));
+
+        let path = astar
+            .calculate_path(Vector2::new(0, 5), Vector2::new(8, 5))
+            .unwrap();
+        assert!(path.iter().all(|position| position.y < 7));
+    }
+
+    #[test]
+    fn test_object_avoidance() {
+        let mut grid = create_empty_grid();
+        let opponent = Vector2::new(4.0, 2.0);
+        grid.upsert_hybrid_object(
+            0,
+            HybridObject {
+                position: opponent,
+                radius: 1.0,
+                class: ObjectClass::OpponentRobot,
+                timestamp: 0.0,
+                time_to_live: None,
+            },
+        );
+
+        let mut avoidance = ObjectAvoidance::default();
+        avoidance.set_class(
+            ObjectClass::OpponentRobot,
+            ClassAvoidance {
+                clearance: 1.0,
+                cost: 0.0,
+                hard: true,
+            },
+        );
+        let astar = AStar::build_with_costmap(
+            grid,
+            NodePickStyle::ALL,
+            Costmap::from_object_avoidance(
+                NodeRadiusSearch {
+                    node_radius_search_radius_squared: 1.0,
+                    do_absolute_discard: false,
+                    avg_distance_min_discard_threshold: 1.0,
+                    avg_distance_cost: 1.0,
+                },
+                avoidance,
+            ),
+        );
+
+        let path = astar
+            .calculate_path(Vector2::new(0, 2), Vector2::new(8, 2))
+            .unwrap();
+        assert!(path.iter().all(|position| {
+            (Vector2::new(position.x as f32, position.y as f32) - opponent).magnitude() > 2.0
+        }));
+    }
+
+    #[test]
+    fn test_opponent_heatmap() {
+        let mut grid = create_empty_grid();
+        for y in 0..8 {
+            grid.add_opponent_heat(Vector2::new(2.0, y as f32), 0.5, 100.0);
+        }
+        let astar = AStar::new(grid);
+
+        let path = astar
+            .calculate_path(Vector2::new(0, 0), Vector2::new(4, 0))
+            .unwrap();
+        assert!(path
+            .iter()
+            .all(|position| position.x != 2 || position.y >= 8));
+    }
+
+    #[test]
+    fn test_keep_out_zones() {
+        let mut grid = create_empty_grid();
+        let wall: Vec<Vector2<i32>> = (0..9).map(|i| Vector2::new(2, i)).collect();
+        grid.add_keep_out_zone(KeepOutZone::new(
+            wall.clone(),
+            vec![TimeWindow::new(0.0, 15.0)],
+        ));
+        let start = Vector2::new(0, 0);
+        let end = Vector2::new(4, 0);
+
+        let mut astar = AStar::new(grid);
+        let path = astar.calculate_path(start, end).unwrap();
+        assert!(path.contains(&Vector2::new(2, 9)));
+
+        astar.get_grid().set_time(20.0);
+        let path = astar.calculate_path(start, end).unwrap();
+        assert!(path.iter().any(|position| wall.contains(position)));
+
+        // At one cell per second the wall is only reached once the zone is over
+        astar.get_grid().set_time(13.5);
+        assert!(!astar.validate_path(&path).is_valid());
+        astar.set_travel_speed(Some(1.0));
+        assert!(astar.validate_path(&path).is_valid());
+
+        // The zones are enforced by the built-in layer
+        astar.set_travel_speed(None);
+        assert!(astar.get_costmap_mut().remove_layer(KEEP_OUT_LAYER));
+        assert!(astar.validate_path(&path).is_valid());
+    }
 }
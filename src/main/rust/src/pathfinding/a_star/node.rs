@@ -92,6 +92,14 @@ impl Node {
     pub fn get_cost(&self) -> f64 {
         return self.cost;
     }
+
+    pub fn set_time_ms_since_initial(&mut self, time_ms: f64) {
+        self.time_ms_since_initial = time_ms;
+    }
+
+    pub fn get_time_ms_since_initial(&self) -> f64 {
+        self.time_ms_since_initial
+    }
 }
 
 impl Ord for Node {
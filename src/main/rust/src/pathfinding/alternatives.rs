@@ -0,0 +1,175 @@
+use std::collections::{HashMap, HashSet};
+
+use nalgebra::Vector2;
+
+use super::{OverlayPathfinding, PathCost};
+
+pub struct AlternativePathConfig {
+    /// How many paths to return at most, including the cheapest one
+    pub path_count: usize,
+    /// The largest share of a path's positions that may lie in the corridor of an already
+    /// accepted path for it to count as a different route
+    pub max_overlap: f64,
+    /// How far around a path (in cells) its corridor reaches
+    pub corridor_radius: i32,
+    /// The cost added to every position in the corridor of each path found so far
+    pub penalty: f64,
+    /// How many searches are made at most before giving up on finding `path_count` paths
+    pub max_attempts: usize,
+}
+
+pub struct AlternativePath {
+    pub path: Vec<Vector2<i32>>,
+    /// The cost of the path under the planner's own cost model, without any diversity penalty
+    pub cost: f64,
+}
+
+/// # Returns
+/// Up to `config.path_count` meaningfully different paths from `start` to `end`, cheapest first.
+/// The first path is the planner's optimum. Every following search penalizes the corridors of all
+/// paths found before it, and a result is only kept if it overlaps each accepted path by at most
+/// `config.max_overlap`.
+/// # Arguments
+/// * `planner` - The pathfinder used for every search
+/// * `start` - The start position
+/// * `end` - The goal position
+/// * `config` - How many paths to look for and how different they have to be
+pub fn calculate_alternative_paths<P: OverlayPathfinding + PathCost>(
+    planner: &P,
+    start: Vector2<i32>,
+    end: Vector2<i32>,
+    config: &AlternativePathConfig,
+) -> Vec<AlternativePath> {
+    let mut accepted: Vec<(AlternativePath, HashSet<Vector2<i32>>)> = Vec::new();
+    let mut penalties: HashMap<Vector2<i32>, f64> = HashMap::new();
+
+    for _ in 0..config.max_attempts {
+        if accepted.len() >= config.path_count {
+            break;
+        }
+
+        let overlay = |position: Vector2<i32>| *penalties.get(&position).unwrap_or(&0.0);
+        let Some(path) = planner.calculate_path_with_overlay(start, end, &overlay) else {
+            break;
+        };
+
+        let corridor = get_corridor(&path, config.corridor_radius);
+        for position in &corridor {
+            *penalties.entry(*position).or_insert(0.0) += config.penalty;
+        }
+
+        let is_diverse = accepted
+            .iter()
+            .all(|(_, other)| get_overlap(&path, other) <= config.max_overlap);
+        if !is_diverse {
+            continue;
+        }
+
+        if let Some(cost) = planner.path_cost(&path) {
+            accepted.push((AlternativePath { path, cost }, corridor));
+        }
+    }
+
+    let mut paths: Vec<AlternativePath> = accepted.into_iter().map(|(path, _)| path).collect();
+    paths.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+    paths
+}
+
+fn get_corridor(path: &[Vector2<i32>], radius: i32) -> HashSet<Vector2<i32>> {
+    let mut corridor = HashSet::new();
+    for position in path {
+        for i in -radius..=radius {
+            for j in -radius..=radius {
+                corridor.insert(position + Vector2::new(i, j));
+            }
+        }
+    }
+
+    corridor
+}
+
+/// # Returns
+/// The share of the positions of `path` that lie inside `corridor`. Start and end are left out
+/// since every alternative shares them.
+fn get_overlap(path: &[Vector2<i32>], corridor: &HashSet<Vector2<i32>>) -> f64 {
+    if path.len() <= 2 {
+        return 1.0;
+    }
+
+    let inner = &path[1..path.len() - 1];
+    let shared = inner
+        .iter()
+        .filter(|position| corridor.contains(position))
+        .count();
+    shared as f64 / inner.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_grid::HybridGrid;
+    use crate::pathfinding::a_star::AStar;
+    use crate::pathfinding::Pathfinding;
+
+    fn create_config(path_count: usize, max_overlap: f64) -> AlternativePathConfig {
+        AlternativePathConfig {
+            path_count,
+            max_overlap,
+            corridor_radius: 1,
+            penalty: 10.0,
+            max_attempts: 20,
+        }
+    }
+
+    #[test]
+    fn test_first_path_is_optimal() {
+        let astar = AStar::new(HybridGrid::new(20, 20, 1.0, vec![], 10, 10));
+        let start = Vector2::new(2, 2);
+        let end = Vector2::new(17, 17);
+
+        let paths = calculate_alternative_paths(&astar, start, end, &create_config(3, 0.5));
+        let best = astar.calculate_path(start, end).unwrap();
+
+        assert!(!paths.is_empty());
+        assert!((paths[0].cost - astar.path_cost(&best).unwrap()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_alternatives_are_diverse() {
+        let astar = AStar::new(HybridGrid::new(20, 20, 1.0, vec![], 10, 10));
+        let config = create_config(3, 0.5);
+
+        let paths =
+            calculate_alternative_paths(&astar, Vector2::new(2, 2), Vector2::new(17, 17), &config);
+
+        assert!(paths.len() >= 2);
+        for (i, path) in paths.iter().enumerate() {
+            assert_eq!(path.path.first(), Some(&Vector2::new(2, 2)));
+            assert_eq!(path.path.last(), Some(&Vector2::new(17, 17)));
+            for other in &paths[i + 1..] {
+                let radius = config.corridor_radius;
+                let overlap = get_overlap(&path.path, &get_corridor(&other.path, radius))
+                    .min(get_overlap(&other.path, &get_corridor(&path.path, radius)));
+                assert!(overlap <= config.max_overlap);
+            }
+        }
+        assert!(paths.windows(2).all(|pair| pair[0].cost <= pair[1].cost));
+    }
+
+    #[test]
+    fn test_no_path() {
+        let mut grid = HybridGrid::new(10, 10, 1.0, vec![], 5, 5);
+        for i in 0..10 {
+            grid.push_static_obstacle(Vector2::new(5, i));
+        }
+        let astar = AStar::new(grid);
+
+        let paths = calculate_alternative_paths(
+            &astar,
+            Vector2::new(0, 0),
+            Vector2::new(9, 9),
+            &create_config(3, 0.5),
+        );
+        assert!(paths.is_empty());
+    }
+}
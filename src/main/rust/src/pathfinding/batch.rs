@@ -0,0 +1,92 @@
+use std::sync::{mpsc, Arc};
+
+use nalgebra::Vector2;
+use threadpool::ThreadPool;
+
+use super::Pathfinding;
+
+/// Solves many (start, end) queries in parallel against one shared, read-only planner. The thread
+/// pool is kept between batches so it can be reused every cycle.
+pub struct BatchPlanner {
+    pool: ThreadPool,
+}
+
+impl BatchPlanner {
+    pub fn new(thread_count: usize) -> Self {
+        Self {
+            pool: ThreadPool::new(thread_count.max(1)),
+        }
+    }
+
+    pub fn get_thread_count(&self) -> usize {
+        self.pool.max_count()
+    }
+
+    /// # Returns
+    /// The path for every query, in the same order as `queries`. A query whose search panicked is
+    /// reported as having no path.
+    /// # Arguments
+    /// * `planner` - The pathfinder shared by all queries
+    /// * `queries` - The (start, end) pairs to solve
+    pub fn calculate_paths<P: Pathfinding + Send + Sync + 'static>(
+        &self,
+        planner: Arc<P>,
+        queries: &[(Vector2<i32>, Vector2<i32>)],
+    ) -> Vec<Option<Vec<Vector2<i32>>>> {
+        let (sender, receiver) = mpsc::channel();
+        for (i, &(start, end)) in queries.iter().enumerate() {
+            let planner = planner.clone();
+            let sender = sender.clone();
+            self.pool.execute(move || {
+                let _ = sender.send((i, planner.calculate_path(start, end)));
+            });
+        }
+        drop(sender);
+
+        let mut results = vec![None; queries.len()];
+        for (i, path) in receiver {
+            results[i] = path;
+        }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_grid::HybridGrid;
+    use crate::pathfinding::a_star::AStar;
+
+    #[test]
+    fn test_results_keep_query_order() {
+        let mut grid = HybridGrid::new(20, 20, 1.0, vec![], 10, 10);
+        for i in 0..20 {
+            grid.push_static_obstacle(Vector2::new(15, i));
+        }
+        let astar = Arc::new(AStar::new(grid));
+
+        let queries: Vec<(Vector2<i32>, Vector2<i32>)> = (0..12)
+            .map(|i| (Vector2::new(0, 0), Vector2::new(i + 5, 19 - i)))
+            .collect();
+
+        let batch_planner = BatchPlanner::new(4);
+        let results = batch_planner.calculate_paths(astar.clone(), &queries);
+
+        assert_eq!(results.len(), queries.len());
+        for (query, result) in queries.iter().zip(&results) {
+            assert_eq!(*result, astar.calculate_path(query.0, query.1));
+        }
+        assert!(results[0].is_some());
+        assert!(results[11].is_none());
+    }
+
+    #[test]
+    fn test_empty_batch() {
+        let astar = Arc::new(AStar::new(HybridGrid::new(10, 10, 1.0, vec![], 5, 5)));
+        let batch_planner = BatchPlanner::new(0);
+
+        assert_eq!(batch_planner.get_thread_count(), 1);
+        assert!(batch_planner.calculate_paths(astar, &[]).is_empty());
+    }
+}
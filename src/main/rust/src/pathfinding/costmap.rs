@@ -0,0 +1,619 @@
+use std::{collections::HashMap, sync::Arc};
+
+use nalgebra::Vector2;
+
+use super::NodeRadiusSearch;
+use crate::hybrid_grid::{
+    hybrid_objects::ObjectClass, uncertainty_fields::FieldCombine, HybridGrid,
+};
+
+pub const STATIC_OBSTACLE_LAYER: &str = "static_obstacles";
+pub const OBSERVED_OBSTACLE_LAYER: &str = "observed_obstacles";
+pub const KEEP_OUT_LAYER: &str = "keep_out";
+pub const UNKNOWN_LAYER: &str = "unknown";
+pub const CLEARANCE_LAYER: &str = "clearance";
+pub const UNCERTAINTY_LAYER: &str = "uncertainty";
+pub const LOCAL_GRID_LAYER: &str = "local_grid";
+pub const OBJECT_AVOIDANCE_LAYER: &str = "object_avoidance";
+pub const OPPONENT_HEATMAP_LAYER: &str = "opponent_heatmap";
+
+/// The cell a costmap is asked about.
+pub struct CellQuery {
+    pub position: Vector2<i32>,
+    /// Whether the cell is the goal of the search. Layers that refuse cells near obstacles should
+    /// still let the goal through.
+    pub is_goal: bool,
+    /// The match time the cell would be entered at, in seconds
+    pub time: f64,
+}
+
+/// One source of cost in a `Costmap`.
+pub trait CostLayer: Send + Sync {
+    /// # Returns
+    /// The cost of entering the queried cell, `f64::INFINITY` if it can't be entered at all, or
+    /// `None` if this layer has no opinion about the cell.
+    fn get_cost(&self, grid: &HybridGrid, query: &CellQuery) -> Option<f64>;
+}
+
+/// How a layer's cost is merged into the cost of the layers below it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CombineRule {
+    Max,
+    Sum,
+    /// Replaces the cost below wherever the layer has an opinion, which can also make a lethal
+    /// cell passable again
+    Override,
+}
+
+#[derive(Clone)]
+struct CostmapEntry {
+    name: String,
+    layer: Arc<dyn CostLayer>,
+    rule: CombineRule,
+}
+
+/// An ordered stack of cost layers. The master cost of a cell starts at 0 and every layer, from
+/// the bottom up, is merged in with its own rule.
+#[derive(Clone, Default)]
+pub struct Costmap {
+    layers: Vec<CostmapEntry>,
+}
+
+impl Costmap {
+    /// # Returns
+    /// A costmap without any layers, under which every cell is free.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Returns
+    /// The built-in stack: static obstacles, obstacles observed by sensors, active keep-out zones,
+    /// unknown cells treated as free, then the clearance term, uncertainty fields and where
+    /// opponents have been.
+    pub fn from_node_radius_search(config: NodeRadiusSearch) -> Self {
+        Self::build(config, None)
+    }
+
+    /// # Returns
+    /// The built-in stack, except that hybrid objects are kept out of the clearance term and are
+    /// avoided by class right after it instead.
+    pub fn from_object_avoidance(config: NodeRadiusSearch, avoidance: ObjectAvoidance) -> Self {
+        Self::build(config, Some(avoidance))
+    }
+
+    fn build(config: NodeRadiusSearch, avoidance: Option<ObjectAvoidance>) -> Self {
+        let mut costmap = Self::new();
+        costmap.push_layer(STATIC_OBSTACLE_LAYER, StaticObstacleLayer, CombineRule::Max);
+        costmap.push_layer(
+            OBSERVED_OBSTACLE_LAYER,
+            ObservedObstacleLayer,
+            CombineRule::Max,
+        );
+        costmap.push_layer(KEEP_OUT_LAYER, KeepOutLayer, CombineRule::Max);
+        costmap.push_layer(
+            UNKNOWN_LAYER,
+            UnknownLayer {
+                policy: UnknownPolicy::Free,
+            },
+            CombineRule::Sum,
+        );
+        costmap.push_layer(
+            CLEARANCE_LAYER,
+            ClearanceLayer {
+                config,
+                include_hybrid_objects: avoidance.is_none(),
+            },
+            CombineRule::Sum,
+        );
+        if let Some(avoidance) = avoidance {
+            costmap.push_layer(
+                OBJECT_AVOIDANCE_LAYER,
+                ObjectAvoidanceLayer { avoidance },
+                CombineRule::Sum,
+            );
+        }
+        costmap.push_layer(
+            UNCERTAINTY_LAYER,
+            UncertaintyLayer {
+                combine: FieldCombine::Sum,
+            },
+            CombineRule::Sum,
+        );
+        costmap.push_layer(
+            OPPONENT_HEATMAP_LAYER,
+            OpponentHeatmapLayer,
+            CombineRule::Sum,
+        );
+
+        costmap
+    }
+
+    /// Adds a layer on top of the stack, replacing any layer with the same name in place.
+    pub fn push_layer(&mut self, name: &str, layer: impl CostLayer + 'static, rule: CombineRule) {
+        let entry = CostmapEntry {
+            name: name.to_string(),
+            layer: Arc::new(layer),
+            rule,
+        };
+
+        match self.layers.iter_mut().find(|other| other.name == name) {
+            Some(other) => *other = entry,
+            None => self.layers.push(entry),
+        }
+    }
+
+    /// Adds a layer at `index` in the stack, 0 being the bottom. Any layer with the same name is
+    /// removed first.
+    pub fn insert_layer(
+        &mut self,
+        index: usize,
+        name: &str,
+        layer: impl CostLayer + 'static,
+        rule: CombineRule,
+    ) {
+        self.remove_layer(name);
+        self.layers.insert(
+            index.min(self.layers.len()),
+            CostmapEntry {
+                name: name.to_string(),
+                layer: Arc::new(layer),
+                rule,
+            },
+        );
+    }
+
+    /// # Returns
+    /// Whether there was a layer named `name` to remove.
+    pub fn remove_layer(&mut self, name: &str) -> bool {
+        let before = self.layers.len();
+        self.layers.retain(|entry| entry.name != name);
+
+        self.layers.len() != before
+    }
+
+    pub fn set_combine_rule(&mut self, name: &str, rule: CombineRule) {
+        if let Some(entry) = self.layers.iter_mut().find(|entry| entry.name == name) {
+            entry.rule = rule;
+        }
+    }
+
+    /// # Returns
+    /// The names of all layers from the bottom of the stack up.
+    pub fn get_layer_names(&self) -> Vec<&str> {
+        self.layers
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect()
+    }
+
+    /// # Returns
+    /// The master cost of the queried cell, `f64::INFINITY` if it is lethal.
+    pub fn get_cost(&self, grid: &HybridGrid, query: &CellQuery) -> f64 {
+        self.layers.iter().fold(0.0, |master, entry| {
+            match entry.layer.get_cost(grid, query) {
+                Some(cost) => match entry.rule {
+                    CombineRule::Max => master.max(cost),
+                    CombineRule::Sum => master + cost,
+                    CombineRule::Override => cost,
+                },
+                None => master,
+            }
+        })
+    }
+}
+
+/// Makes static obstacles lethal.
+pub struct StaticObstacleLayer;
+
+impl CostLayer for StaticObstacleLayer {
+    fn get_cost(&self, grid: &HybridGrid, query: &CellQuery) -> Option<f64> {
+        if grid.is_obstructed(query.position) {
+            Some(f64::INFINITY)
+        } else {
+            None
+        }
+    }
+}
+
+/// Makes cells whose occupancy belief crossed the obstacle threshold lethal.
+pub struct ObservedObstacleLayer;
+
+impl CostLayer for ObservedObstacleLayer {
+    fn get_cost(&self, grid: &HybridGrid, query: &CellQuery) -> Option<f64> {
+        grid.is_observed_obstacle(query.position)
+            .then_some(f64::INFINITY)
+    }
+}
+
+/// Makes cells lethal while a keep-out zone covering them is active.
+pub struct KeepOutLayer;
+
+impl CostLayer for KeepOutLayer {
+    fn get_cost(&self, grid: &HybridGrid, query: &CellQuery) -> Option<f64> {
+        grid.is_kept_out(query.position, query.time)
+            .then_some(f64::INFINITY)
+    }
+}
+
+/// How planners treat cells nothing is known about.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnknownPolicy {
+    Free,
+    Occupied,
+    /// Passable at this extra cost per cell
+    Cost(f64),
+}
+
+pub struct UnknownLayer {
+    pub policy: UnknownPolicy,
+}
+
+impl CostLayer for UnknownLayer {
+    fn get_cost(&self, grid: &HybridGrid, query: &CellQuery) -> Option<f64> {
+        if !grid.is_unknown(query.position) {
+            return None;
+        }
+
+        match self.policy {
+            UnknownPolicy::Free => None,
+            UnknownPolicy::Occupied => Some(f64::INFINITY),
+            UnknownPolicy::Cost(cost) => Some(cost),
+        }
+    }
+}
+
+/// Charges cells by how close static obstacles and hybrid objects are around them, and optionally
+/// refuses cells that are too crowded.
+pub struct ClearanceLayer {
+    pub config: NodeRadiusSearch,
+    /// Off when an `ObjectAvoidanceLayer` takes care of hybrid objects
+    pub include_hybrid_objects: bool,
+}
+
+impl CostLayer for ClearanceLayer {
+    fn get_cost(&self, grid: &HybridGrid, query: &CellQuery) -> Option<f64> {
+        let position = query.position;
+        let static_nodes_in_radius = grid.get_all_obstructions_in_radius(
+            position,
+            self.config.node_radius_search_radius_squared as i32,
+        );
+        let mut all_distances = static_nodes_in_radius
+            .iter()
+            .map(|node| get_distance_squared(&position, node))
+            .collect::<Vec<f32>>();
+        if self.include_hybrid_objects {
+            let hybrid_nodes_in_radius =
+                grid.get_nearest(position, self.config.node_radius_search_radius_squared);
+            all_distances.extend(hybrid_nodes_in_radius.iter().map(|node| node.distance));
+        }
+
+        let avg_distance = average_distance(&all_distances);
+        if !query.is_goal
+            && self.config.do_absolute_discard
+            && avg_distance != 0.0
+            && avg_distance <= self.config.avg_distance_min_discard_threshold
+        {
+            return Some(f64::INFINITY);
+        }
+
+        Some((avg_distance * self.config.avg_distance_cost) as f64)
+    }
+}
+
+/// How cells near hybrid objects of one class are treated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClassAvoidance {
+    /// How far from the object's edge cells are affected, in grid cells
+    pub clearance: f32,
+    /// The extra cost at the object's edge, fading linearly to 0 at `clearance`
+    pub cost: f32,
+    /// Whether affected cells are refused instead of charged
+    pub hard: bool,
+}
+
+/// Per-class treatment of hybrid objects, e.g. a wide berth around opponents and none around game
+/// pieces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectAvoidance {
+    classes: HashMap<ObjectClass, ClassAvoidance>,
+    /// Used for the classes that were not configured
+    default: ClassAvoidance,
+}
+
+impl Default for ObjectAvoidance {
+    /// Refuses cells inside any object and nothing else.
+    fn default() -> Self {
+        Self::new(ClassAvoidance {
+            clearance: 0.0,
+            cost: 0.0,
+            hard: true,
+        })
+    }
+}
+
+impl ObjectAvoidance {
+    pub fn new(default: ClassAvoidance) -> Self {
+        Self {
+            classes: HashMap::new(),
+            default,
+        }
+    }
+
+    pub fn set_class(&mut self, class: ObjectClass, avoidance: ClassAvoidance) {
+        self.classes.insert(class, avoidance);
+    }
+
+    pub fn get_class(&self, class: ObjectClass) -> &ClassAvoidance {
+        self.classes.get(&class).unwrap_or(&self.default)
+    }
+
+    fn get_max_clearance(&self) -> f32 {
+        self.classes
+            .values()
+            .fold(self.default.clearance, |max, avoidance| {
+                max.max(avoidance.clearance)
+            })
+    }
+}
+
+/// Charges or refuses cells near hybrid objects according to their class. Hard margins still let
+/// the goal through.
+pub struct ObjectAvoidanceLayer {
+    pub avoidance: ObjectAvoidance,
+}
+
+impl CostLayer for ObjectAvoidanceLayer {
+    fn get_cost(&self, grid: &HybridGrid, query: &CellQuery) -> Option<f64> {
+        let position = Vector2::new(query.position.x as f32, query.position.y as f32);
+        let objects = grid.get_hybrid_objects_near(position, self.avoidance.get_max_clearance());
+
+        let mut cost = None;
+        for (_, object) in objects {
+            let avoidance = self.avoidance.get_class(object.class);
+            let distance = object.get_edge_distance(position);
+            if distance > avoidance.clearance {
+                continue;
+            }
+
+            if avoidance.hard {
+                if !query.is_goal {
+                    return Some(f64::INFINITY);
+                }
+                continue;
+            }
+
+            let falloff = if avoidance.clearance > 0.0 {
+                1.0 - distance / avoidance.clearance
+            } else {
+                1.0
+            };
+            cost = Some(cost.unwrap_or(0.0) + (avoidance.cost * falloff) as f64);
+        }
+
+        cost
+    }
+}
+
+/// Charges cells inside uncertainty fields, combining every field that covers them.
+pub struct UncertaintyLayer {
+    pub combine: FieldCombine,
+}
+
+impl CostLayer for UncertaintyLayer {
+    fn get_cost(&self, grid: &HybridGrid, query: &CellQuery) -> Option<f64> {
+        let position = Vector2::new(query.position.x as f32, query.position.y as f32);
+
+        grid.get_uncertenty_cost(position, self.combine)
+            .map(|cost| cost as f64)
+    }
+}
+
+/// Charges cells by the opponent heat on them, so searches learn to stay out of the lanes
+/// opponents keep using even while none is there.
+pub struct OpponentHeatmapLayer;
+
+impl CostLayer for OpponentHeatmapLayer {
+    fn get_cost(&self, grid: &HybridGrid, query: &CellQuery) -> Option<f64> {
+        let heat = grid.get_opponent_heat(query.position);
+
+        (heat > 0.0).then_some(heat as f64)
+    }
+}
+
+/// Brings a second grid, usually a `RollingWindow` around the robot, into planning on the field
+/// grid. A field cell is lethal if any local cell inside it is a static or observed obstacle, and
+/// otherwise costs as much as the most expensive local cell inside it. Cells the local grid
+/// doesn't cover are left to the other layers.
+pub struct LocalGridLayer {
+    local_grid: Arc<HybridGrid>,
+}
+
+impl LocalGridLayer {
+    pub fn new(local_grid: Arc<HybridGrid>) -> Self {
+        Self { local_grid }
+    }
+}
+
+impl CostLayer for LocalGridLayer {
+    fn get_cost(&self, grid: &HybridGrid, query: &CellQuery) -> Option<f64> {
+        // Shrunk a little so cells only touching the field cell's border are left out
+        let half_size = Vector2::repeat(grid.get_square_size_meters() * 0.499);
+        let center = grid.cell_to_meters(query.position);
+        let (window_min, window_max) = self.local_grid.get_cell_bounds();
+        let min = self
+            .local_grid
+            .meters_to_cell(center - half_size)
+            .sup(&window_min);
+        let max = self
+            .local_grid
+            .meters_to_cell(center + half_size)
+            .inf(&window_max);
+        if min.x > max.x || min.y > max.y {
+            return None;
+        }
+
+        let mut cost = None;
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                let cell = Vector2::new(x, y);
+                if self.local_grid.is_obstructed(cell) || self.local_grid.is_observed_obstacle(cell)
+                {
+                    return Some(f64::INFINITY);
+                }
+
+                let cell_cost = self.local_grid.get_cell_cost(cell) as f64;
+                if cell_cost > 0.0 {
+                    cost = Some(cost.unwrap_or(0.0f64).max(cell_cost));
+                }
+            }
+        }
+
+        cost
+    }
+}
+
+fn average_distance(nodes: &[f32]) -> f32 {
+    if nodes.is_empty() {
+        return 0.0;
+    }
+
+    nodes.iter().sum::<f32>() / nodes.len() as f32
+}
+
+fn get_distance_squared(node: &Vector2<i32>, other: &Vector2<i32>) -> f32 {
+    (node.x as f32 - other.x as f32).powi(2) + (node.y as f32 - other.y as f32).powi(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_grid::hybrid_objects::HybridObject;
+
+    struct ConstantLayer(Option<f64>);
+
+    impl CostLayer for ConstantLayer {
+        fn get_cost(&self, _grid: &HybridGrid, _query: &CellQuery) -> Option<f64> {
+            self.0
+        }
+    }
+
+    fn get_cost(costmap: &Costmap, grid: &HybridGrid, position: Vector2<i32>) -> f64 {
+        costmap.get_cost(
+            grid,
+            &CellQuery {
+                position,
+                is_goal: false,
+                time: grid.get_time(),
+            },
+        )
+    }
+
+    #[test]
+    fn test_combine_rules() {
+        let grid = HybridGrid::new(10, 10, 1.0, vec![], 5, 5);
+        let mut costmap = Costmap::new();
+        let position = Vector2::new(1, 1);
+
+        costmap.push_layer("a", ConstantLayer(Some(2.0)), CombineRule::Sum);
+        costmap.push_layer("b", ConstantLayer(Some(3.0)), CombineRule::Sum);
+        assert_eq!(get_cost(&costmap, &grid, position), 5.0);
+
+        costmap.set_combine_rule("b", CombineRule::Max);
+        assert_eq!(get_cost(&costmap, &grid, position), 3.0);
+
+        costmap.push_layer("c", ConstantLayer(None), CombineRule::Override);
+        assert_eq!(get_cost(&costmap, &grid, position), 3.0);
+
+        costmap.insert_layer(0, "d", ConstantLayer(Some(10.0)), CombineRule::Sum);
+        costmap.push_layer("e", ConstantLayer(Some(1.0)), CombineRule::Override);
+        assert_eq!(costmap.get_layer_names(), vec!["d", "a", "b", "c", "e"]);
+        assert_eq!(get_cost(&costmap, &grid, position), 1.0);
+
+        assert!(costmap.remove_layer("e"));
+        assert!(!costmap.remove_layer("e"));
+        assert_eq!(get_cost(&costmap, &grid, position), 12.0);
+    }
+
+    #[test]
+    fn test_override_clears_lethal_cells() {
+        let grid = HybridGrid::new(10, 10, 1.0, vec![Vector2::new(1, 1)], 5, 5);
+        let mut costmap = Costmap::from_node_radius_search(NodeRadiusSearch {
+            node_radius_search_radius_squared: 1.0,
+            do_absolute_discard: false,
+            avg_distance_min_discard_threshold: 1.0,
+            avg_distance_cost: 1.0,
+        });
+        assert_eq!(get_cost(&costmap, &grid, Vector2::new(1, 1)), f64::INFINITY);
+
+        costmap.push_layer("gate", ConstantLayer(Some(0.5)), CombineRule::Override);
+        assert_eq!(get_cost(&costmap, &grid, Vector2::new(1, 1)), 0.5);
+    }
+
+    #[test]
+    fn test_local_grid_layer() {
+        let grid = HybridGrid::new(10, 10, 1.0, vec![], 5, 5);
+        let mut local_grid = HybridGrid::new(20, 20, 0.25, vec![], 10, 10);
+        local_grid.push_static_obstacle(local_grid.meters_to_cell(Vector2::new(2.6, 2.3)));
+        local_grid.set_cell_cost(local_grid.meters_to_cell(Vector2::new(1.1, 1.9)), 4.0);
+
+        let mut costmap = Costmap::new();
+        costmap.push_layer(
+            LOCAL_GRID_LAYER,
+            LocalGridLayer::new(Arc::new(local_grid)),
+            CombineRule::Max,
+        );
+
+        assert_eq!(get_cost(&costmap, &grid, Vector2::new(2, 2)), f64::INFINITY);
+        assert_eq!(get_cost(&costmap, &grid, Vector2::new(1, 1)), 4.0);
+        assert_eq!(get_cost(&costmap, &grid, Vector2::new(3, 3)), 0.0);
+        // Outside the local window
+        assert_eq!(get_cost(&costmap, &grid, Vector2::new(8, 8)), 0.0);
+    }
+
+    #[test]
+    fn test_object_avoidance() {
+        let mut grid = HybridGrid::new(20, 20, 1.0, vec![], 10, 10);
+        let object = |x: f32, class: ObjectClass| HybridObject {
+            position: Vector2::new(x, 10.0),
+            radius: 1.0,
+            class,
+            timestamp: 0.0,
+            time_to_live: None,
+        };
+        grid.upsert_hybrid_object(0, object(3.0, ObjectClass::OpponentRobot));
+        grid.upsert_hybrid_object(1, object(15.0, ObjectClass::GamePiece));
+
+        let mut avoidance = ObjectAvoidance::default();
+        avoidance.set_class(
+            ObjectClass::OpponentRobot,
+            ClassAvoidance {
+                clearance: 2.0,
+                cost: 0.0,
+                hard: true,
+            },
+        );
+        avoidance.set_class(
+            ObjectClass::GamePiece,
+            ClassAvoidance {
+                clearance: 2.0,
+                cost: 4.0,
+                hard: false,
+            },
+        );
+        let mut costmap = Costmap::new();
+        costmap.push_layer(
+            OBJECT_AVOIDANCE_LAYER,
+            ObjectAvoidanceLayer { avoidance },
+            CombineRule::Sum,
+        );
+
+        assert_eq!(
+            get_cost(&costmap, &grid, Vector2::new(5, 10)),
+            f64::INFINITY
+        );
+        assert_eq!(get_cost(&costmap, &grid, Vector2::new(7, 10)), 0.0);
+        assert_eq!(get_cost(&costmap, &grid, Vector2::new(15, 10)), 4.0);
+        assert_eq!(get_cost(&costmap, &grid, Vector2::new(17, 10)), 2.0);
+        assert_eq!(get_cost(&costmap, &grid, Vector2::new(19, 10)), 0.0);
+    }
+}
@@ -0,0 +1,216 @@
+use nalgebra::Vector2;
+
+use super::{OverlayPathfinding, PathCost};
+
+pub struct ReplanConfig {
+    /// How much cheaper a new path has to be than the rest of the previous one before it is
+    /// switched to, as a fraction of the previous path's cost (0.1 = 10% cheaper)
+    pub switch_margin: f64,
+    /// The cost added per cell of distance between a position and the previous path while
+    /// searching, which keeps new paths close to the old one. 0 disables the term.
+    pub deviation_cost: f64,
+}
+
+/// Wraps a pathfinder and remembers the path it handed out last, so that calling it every loop
+/// doesn't flip between near-equal routes. The previous path is kept until it becomes invalid or
+/// a new path beats it by the configured margin.
+pub struct Replanner<P: OverlayPathfinding + PathCost> {
+    planner: P,
+    config: ReplanConfig,
+    previous: Option<(Vector2<i32>, Vec<Vector2<i32>>)>,
+}
+
+impl<P: OverlayPathfinding + PathCost> Replanner<P> {
+    pub fn new(planner: P, config: ReplanConfig) -> Self {
+        Self {
+            planner,
+            config,
+            previous: None,
+        }
+    }
+
+    pub fn get_planner(&self) -> &P {
+        &self.planner
+    }
+
+    pub fn get_planner_mut(&mut self) -> &mut P {
+        &mut self.planner
+    }
+
+    pub fn get_previous_path(&self) -> Option<&Vec<Vector2<i32>>> {
+        self.previous.as_ref().map(|(_, path)| path)
+    }
+
+    /// Replaces the remembered path, e.g. with a path that was planned ahead of time.
+    pub fn set_previous_path(&mut self, end: Vector2<i32>, path: Vec<Vector2<i32>>) {
+        self.previous = Some((end, path));
+    }
+
+    /// Forgets the previous path so the next call plans from scratch.
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    /// # Returns
+    /// The path to follow from `start` to `end`. This is the rest of the previous path while it is
+    /// still valid and no new path is cheaper by more than the switch margin.
+    /// # Arguments
+    /// * `start` - The current position, which should be on or next to the previous path
+    /// * `end` - The goal position. Changing the goal always replans.
+    pub fn calculate_path(
+        &mut self,
+        start: Vector2<i32>,
+        end: Vector2<i32>,
+    ) -> Option<Vec<Vector2<i32>>> {
+        let remaining = self.get_remaining_path(start, end);
+        let remaining_cost = remaining
+            .as_ref()
+            .and_then(|remaining| self.planner.path_cost(remaining));
+
+        let candidate = match (&remaining, remaining_cost) {
+            (Some(remaining), Some(_)) if self.config.deviation_cost > 0.0 => {
+                let overlay = |position: Vector2<i32>| {
+                    self.config.deviation_cost * get_distance_to_path(position, remaining)
+                };
+                self.planner
+                    .calculate_path_with_overlay(start, end, &overlay)
+            }
+            _ => self.planner.calculate_path(start, end),
+        };
+
+        let chosen = match (remaining, remaining_cost) {
+            (Some(remaining), Some(remaining_cost)) => {
+                let candidate_cost = candidate
+                    .as_ref()
+                    .and_then(|candidate| self.planner.path_cost(candidate));
+                match candidate_cost {
+                    Some(candidate_cost)
+                        if candidate_cost < remaining_cost * (1.0 - self.config.switch_margin) =>
+                    {
+                        candidate
+                    }
+                    _ => Some(remaining),
+                }
+            }
+            _ => candidate,
+        };
+
+        self.previous = chosen.clone().map(|path| (end, path));
+        chosen
+    }
+
+    /// # Returns
+    /// The previous path from the position closest to `start` onwards, starting at `start`. `None`
+    /// if there is no previous path to `end` or `start` is not on or next to it.
+    fn get_remaining_path(
+        &self,
+        start: Vector2<i32>,
+        end: Vector2<i32>,
+    ) -> Option<Vec<Vector2<i32>>> {
+        let (previous_end, previous) = self.previous.as_ref()?;
+        if *previous_end != end {
+            return None;
+        }
+
+        let (closest_index, closest) = previous
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, position)| (*position - start).abs().max())?;
+        if (closest - start).abs().max() > 1 {
+            return None;
+        }
+
+        let mut remaining = vec![start];
+        if *closest == start {
+            remaining.extend_from_slice(&previous[closest_index + 1..]);
+        } else {
+            remaining.extend_from_slice(&previous[closest_index..]);
+        }
+
+        Some(remaining)
+    }
+}
+
+fn get_distance_to_path(position: Vector2<i32>, path: &[Vector2<i32>]) -> f64 {
+    path.iter()
+        .map(|other| {
+            let diff = position - other;
+            (diff.x as f64).hypot(diff.y as f64)
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_grid::HybridGrid;
+    use crate::pathfinding::{a_star::AStar, Pathfinding};
+
+    fn create_replanner(switch_margin: f64) -> Replanner<AStar> {
+        Replanner::new(
+            AStar::new(HybridGrid::new(10, 10, 1.0, vec![], 5, 5)),
+            ReplanConfig {
+                switch_margin,
+                deviation_cost: 0.0,
+            },
+        )
+    }
+
+    fn create_detour() -> Vec<Vector2<i32>> {
+        vec![
+            Vector2::new(0, 0),
+            Vector2::new(1, 0),
+            Vector2::new(2, 1),
+            Vector2::new(3, 2),
+            Vector2::new(3, 3),
+        ]
+    }
+
+    #[test]
+    fn test_keeps_previous_path_within_margin() {
+        let mut replanner = create_replanner(0.2);
+        replanner.set_previous_path(Vector2::new(3, 3), create_detour());
+
+        let path = replanner.calculate_path(Vector2::new(0, 0), Vector2::new(3, 3));
+        assert_eq!(path, Some(create_detour()));
+    }
+
+    #[test]
+    fn test_switches_when_better_by_margin() {
+        let mut replanner = create_replanner(0.05);
+        replanner.set_previous_path(Vector2::new(3, 3), create_detour());
+
+        let path = replanner
+            .calculate_path(Vector2::new(0, 0), Vector2::new(3, 3))
+            .unwrap();
+        assert_eq!(path.len(), 4);
+        assert_eq!(replanner.get_previous_path(), Some(&path));
+    }
+
+    #[test]
+    fn test_switches_when_previous_path_is_invalid() {
+        let mut replanner = create_replanner(0.9);
+        replanner.set_previous_path(Vector2::new(3, 3), create_detour());
+        replanner
+            .get_planner_mut()
+            .get_grid()
+            .push_static_obstacle(Vector2::new(2, 1));
+
+        let path = replanner
+            .calculate_path(Vector2::new(0, 0), Vector2::new(3, 3))
+            .unwrap();
+        assert!(!path.contains(&Vector2::new(2, 1)));
+    }
+
+    #[test]
+    fn test_follows_rest_of_previous_path() {
+        let mut replanner = create_replanner(0.2);
+        replanner.set_previous_path(Vector2::new(3, 3), create_detour());
+
+        let path = replanner.calculate_path(Vector2::new(2, 1), Vector2::new(3, 3));
+        assert_eq!(path, Some(create_detour()[2..].to_vec()));
+
+        let path = replanner.calculate_path(Vector2::new(0, 0), Vector2::new(6, 6));
+        assert_eq!(path.map(|path| path.len()), Some(7));
+    }
+}
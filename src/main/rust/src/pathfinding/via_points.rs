@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use nalgebra::Vector2;
+
+use super::{PathCost, Pathfinding};
+
+/// Above this many via points the visiting order is picked greedily instead of trying every order.
+const MAX_EXACT_REORDER: usize = 7;
+
+/// A path through a series of via points, priced leg by leg.
+pub struct ViaPath {
+    pub path: Vec<Vector2<i32>>,
+    pub cost: f64,
+    /// The order the via points were visited in, as indices into the requested via points.
+    pub order: Vec<usize>,
+}
+
+struct Leg {
+    path: Vec<Vector2<i32>>,
+    cost: f64,
+}
+
+/// Plans each (from, to) leg at most once, since reordering asks for the same legs repeatedly.
+struct LegCache<'a, P: Pathfinding + PathCost> {
+    planner: &'a P,
+    legs: HashMap<(Vector2<i32>, Vector2<i32>), Option<Leg>>,
+}
+
+impl<'a, P: Pathfinding + PathCost> LegCache<'a, P> {
+    fn new(planner: &'a P) -> Self {
+        Self {
+            planner,
+            legs: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, from: Vector2<i32>, to: Vector2<i32>) -> Option<&Leg> {
+        let planner = self.planner;
+        self.legs
+            .entry((from, to))
+            .or_insert_with(|| {
+                let path = planner.calculate_path(from, to)?;
+                let cost = planner.path_cost(&path)?;
+                Some(Leg { path, cost })
+            })
+            .as_ref()
+    }
+
+    fn get_cost(&mut self, from: Vector2<i32>, to: Vector2<i32>) -> Option<f64> {
+        self.get(from, to).map(|leg| leg.cost)
+    }
+}
+
+/// # Returns
+/// A path from `start` to `end` that passes through every via point, or `None` if any leg is
+/// unreachable. Each leg is planned separately and the total cost is the sum of the leg costs,
+/// so a via point is charged once as the goal of the leg that reaches it.
+/// # Arguments
+/// * `planner` - The pathfinder used for every leg
+/// * `start` - The start position
+/// * `via_points` - The positions the path has to pass through
+/// * `end` - The goal position
+/// * `reorder_via_points` - Whether the via points may be visited in the cheapest order instead of
+///   the given one
+pub fn calculate_path_via<P: Pathfinding + PathCost>(
+    planner: &P,
+    start: Vector2<i32>,
+    via_points: &[Vector2<i32>],
+    end: Vector2<i32>,
+    reorder_via_points: bool,
+) -> Option<ViaPath> {
+    let mut legs = LegCache::new(planner);
+
+    let order = if reorder_via_points && via_points.len() > 1 {
+        if via_points.len() <= MAX_EXACT_REORDER {
+            find_cheapest_order(&mut legs, start, via_points, end)?
+        } else {
+            find_greedy_order(&mut legs, start, via_points, end)?
+        }
+    } else {
+        (0..via_points.len()).collect()
+    };
+
+    let mut waypoints = vec![start];
+    waypoints.extend(order.iter().map(|&i| via_points[i]));
+    waypoints.push(end);
+
+    let mut path = vec![start];
+    let mut cost = 0.0;
+    for pair in waypoints.windows(2) {
+        let leg = legs.get(pair[0], pair[1])?;
+        path.extend_from_slice(&leg.path[1..]);
+        cost += leg.cost;
+    }
+
+    Some(ViaPath { path, cost, order })
+}
+
+fn find_cheapest_order<P: Pathfinding + PathCost>(
+    legs: &mut LegCache<P>,
+    start: Vector2<i32>,
+    via_points: &[Vector2<i32>],
+    end: Vector2<i32>,
+) -> Option<Vec<usize>> {
+    let mut search = OrderSearch {
+        legs,
+        via_points,
+        end,
+        visited: vec![false; via_points.len()],
+        order: Vec::new(),
+        best: None,
+    };
+    search.search(start, 0.0);
+
+    search.best.map(|(_, order)| order)
+}
+
+/// Depth-first search over visiting orders that stops exploring once an order is already more
+/// expensive than the best complete one.
+struct OrderSearch<'a, 'b, P: Pathfinding + PathCost> {
+    legs: &'a mut LegCache<'b, P>,
+    via_points: &'a [Vector2<i32>],
+    end: Vector2<i32>,
+    visited: Vec<bool>,
+    order: Vec<usize>,
+    best: Option<(f64, Vec<usize>)>,
+}
+
+impl<P: Pathfinding + PathCost> OrderSearch<'_, '_, P> {
+    fn search(&mut self, from: Vector2<i32>, cost: f64) {
+        if self.is_worse_than_best(cost) {
+            return;
+        }
+
+        if self.order.len() == self.via_points.len() {
+            if let Some(leg_cost) = self.legs.get_cost(from, self.end) {
+                if !self.is_worse_than_best(cost + leg_cost) {
+                    self.best = Some((cost + leg_cost, self.order.clone()));
+                }
+            }
+            return;
+        }
+
+        for i in 0..self.via_points.len() {
+            if self.visited[i] {
+                continue;
+            }
+
+            let via_point = self.via_points[i];
+            let Some(leg_cost) = self.legs.get_cost(from, via_point) else {
+                continue;
+            };
+
+            self.visited[i] = true;
+            self.order.push(i);
+            self.search(via_point, cost + leg_cost);
+            self.order.pop();
+            self.visited[i] = false;
+        }
+    }
+
+    fn is_worse_than_best(&self, cost: f64) -> bool {
+        self.best
+            .as_ref()
+            .is_some_and(|(best_cost, _)| cost >= *best_cost)
+    }
+}
+
+fn find_greedy_order<P: Pathfinding + PathCost>(
+    legs: &mut LegCache<P>,
+    start: Vector2<i32>,
+    via_points: &[Vector2<i32>],
+    end: Vector2<i32>,
+) -> Option<Vec<usize>> {
+    let mut visited = vec![false; via_points.len()];
+    let mut order = Vec::new();
+    let mut current = start;
+
+    while order.len() < via_points.len() {
+        let mut next: Option<(usize, f64)> = None;
+        for i in 0..via_points.len() {
+            if visited[i] {
+                continue;
+            }
+
+            if let Some(leg_cost) = legs.get_cost(current, via_points[i]) {
+                if next.is_none_or(|(_, next_cost)| leg_cost < next_cost) {
+                    next = Some((i, leg_cost));
+                }
+            }
+        }
+
+        let (i, _) = next?;
+        visited[i] = true;
+        order.push(i);
+        current = via_points[i];
+    }
+
+    legs.get_cost(current, end)?;
+    Some(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_grid::HybridGrid;
+    use crate::pathfinding::a_star::AStar;
+
+    fn create_empty_grid() -> HybridGrid {
+        HybridGrid::new(10, 10, 1.0, vec![], 5, 5)
+    }
+
+    #[test]
+    fn test_passes_through_via_points_in_order() {
+        let astar = AStar::new(create_empty_grid());
+        let via_points = [Vector2::new(0, 8), Vector2::new(8, 0)];
+
+        let via_path = calculate_path_via(
+            &astar,
+            Vector2::new(0, 0),
+            &via_points,
+            Vector2::new(8, 8),
+            false,
+        )
+        .unwrap();
+
+        let first = via_path.path.iter().position(|p| *p == via_points[0]);
+        let second = via_path.path.iter().position(|p| *p == via_points[1]);
+        assert!(first.unwrap() < second.unwrap());
+        assert_eq!(via_path.order, vec![0, 1]);
+        assert_eq!(via_path.path.first(), Some(&Vector2::new(0, 0)));
+        assert_eq!(via_path.path.last(), Some(&Vector2::new(8, 8)));
+    }
+
+    #[test]
+    fn test_cost_is_sum_of_legs() {
+        let astar = AStar::new(create_empty_grid());
+        let start = Vector2::new(0, 0);
+        let via = Vector2::new(4, 7);
+        let end = Vector2::new(8, 2);
+
+        let via_path = calculate_path_via(&astar, start, &[via], end, false).unwrap();
+
+        let first_leg = astar.calculate_path(start, via).unwrap();
+        let second_leg = astar.calculate_path(via, end).unwrap();
+        let expected = astar.path_cost(&first_leg).unwrap() + astar.path_cost(&second_leg).unwrap();
+        assert!((via_path.cost - expected).abs() < 1e-9);
+        assert_eq!(via_path.path.len(), first_leg.len() + second_leg.len() - 1);
+    }
+
+    #[test]
+    fn test_reorder_via_points() {
+        let astar = AStar::new(create_empty_grid());
+        let start = Vector2::new(0, 0);
+        let via_points = [Vector2::new(0, 8), Vector2::new(0, 2)];
+        let end = Vector2::new(0, 9);
+
+        let in_order = calculate_path_via(&astar, start, &via_points, end, false).unwrap();
+        let reordered = calculate_path_via(&astar, start, &via_points, end, true).unwrap();
+
+        assert_eq!(reordered.order, vec![1, 0]);
+        assert!(reordered.cost < in_order.cost);
+    }
+
+    #[test]
+    fn test_unreachable_via_point() {
+        let mut grid = create_empty_grid();
+        for i in 0..10 {
+            grid.push_static_obstacle(Vector2::new(5, i));
+        }
+        let astar = AStar::new(grid);
+
+        let via_path = calculate_path_via(
+            &astar,
+            Vector2::new(0, 0),
+            &[Vector2::new(8, 8)],
+            Vector2::new(0, 9),
+            true,
+        );
+        assert!(via_path.is_none());
+    }
+}
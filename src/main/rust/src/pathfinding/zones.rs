@@ -0,0 +1,145 @@
+use nalgebra::Vector2;
+
+use crate::hybrid_grid::{zones::SemanticZone, HybridGrid};
+
+use super::{PathCost, Pathfinding};
+
+/// How many reachable cells of a zone without approach poses are compared, nearest to the start
+/// first.
+const MAX_ZONE_CELL_PATHS: usize = 8;
+
+/// A path into a zone.
+pub struct ZonePath {
+    pub path: Vec<Vector2<i32>>,
+    pub cost: f64,
+    /// The approach pose the path ends at, as an index into the zone's approach poses, or `None`
+    /// if the zone has none and the path ends at a cell inside it
+    pub approach_pose: Option<usize>,
+}
+
+/// # Returns
+/// The cheapest path from `start` to one of the zone's approach poses. For a zone without any, the
+/// cheapest path to one of the `MAX_ZONE_CELL_PATHS` reachable free cells nearest to `start` in a
+/// straight line, skipping cells that can't be reached. `None` if nothing is reachable.
+/// # Arguments
+/// * `planner` - The pathfinder used for every search
+/// * `grid` - The grid the planner searches, used to find the zone's cells
+/// * `start` - The start position
+/// * `zone` - The zone to go to
+pub fn calculate_path_to_zone<P: Pathfinding + PathCost>(
+    planner: &P,
+    grid: &HybridGrid,
+    start: Vector2<i32>,
+    zone: &SemanticZone,
+) -> Option<ZonePath> {
+    if !zone.approach_poses.is_empty() {
+        return zone
+            .approach_poses
+            .iter()
+            .enumerate()
+            .filter_map(|(i, pose)| {
+                let path = planner.calculate_path(start, grid.meters_to_cell(pose.position))?;
+                let cost = planner.path_cost(&path)?;
+                Some(ZonePath {
+                    path,
+                    cost,
+                    approach_pose: Some(i),
+                })
+            })
+            .min_by(|a, b| a.cost.total_cmp(&b.cost));
+    }
+
+    let mut cells: Vec<Vector2<i32>> = zone
+        .get_cells(grid)
+        .into_iter()
+        .filter(|cell| !grid.is_obstructed(*cell))
+        .collect();
+    cells.sort_by_key(|cell| {
+        let offset = cell - start;
+        offset.dot(&offset)
+    });
+
+    let mut best: Option<ZonePath> = None;
+    let mut reached = 0;
+    for cell in cells {
+        if reached == MAX_ZONE_CELL_PATHS {
+            break;
+        }
+
+        let Some(path) = planner.calculate_path(start, cell) else {
+            continue;
+        };
+        let Some(cost) = planner.path_cost(&path) else {
+            continue;
+        };
+        reached += 1;
+
+        if best.as_ref().is_none_or(|best| cost < best.cost) {
+            best = Some(ZonePath {
+                path,
+                cost,
+                approach_pose: None,
+            });
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_grid::zones::ApproachPose;
+    use crate::pathfinding::a_star::AStar;
+
+    #[test]
+    fn test_path_to_zone() {
+        let grid = HybridGrid::new(10, 10, 1.0, vec![], 5, 5);
+        let astar = AStar::new(grid.clone());
+        let zone = SemanticZone::new(
+            "station",
+            vec![
+                Vector2::new(6.0, 6.0),
+                Vector2::new(9.0, 6.0),
+                Vector2::new(9.0, 9.0),
+                Vector2::new(6.0, 9.0),
+            ],
+        );
+
+        let into = calculate_path_to_zone(&astar, &grid, Vector2::new(0, 0), &zone).unwrap();
+        assert_eq!(into.path.last(), Some(&Vector2::new(6, 6)));
+        assert_eq!(into.approach_pose, None);
+
+        let zone = zone.with_approach_poses(vec![
+            ApproachPose::new(Vector2::new(8.5, 2.5), 0.0),
+            ApproachPose::new(Vector2::new(2.5, 5.5), 0.0),
+        ]);
+        let approach = calculate_path_to_zone(&astar, &grid, Vector2::new(0, 0), &zone).unwrap();
+        assert_eq!(approach.path.last(), Some(&Vector2::new(2, 5)));
+        assert_eq!(approach.approach_pose, Some(1));
+    }
+
+    #[test]
+    fn test_nearest_zone_cells_walled_off() {
+        let mut grid = HybridGrid::new(10, 10, 1.0, vec![], 5, 5);
+        let wall: Vec<Vector2<i32>> = (0..=6)
+            .map(|x| Vector2::new(x, 3))
+            .chain((0..=2).map(|y| Vector2::new(6, y)))
+            .collect();
+        grid.set_static_obstacles(&wall, true);
+        let astar = AStar::new(grid.clone());
+        let zone = SemanticZone::new(
+            "station",
+            vec![
+                Vector2::new(1.0, 0.0),
+                Vector2::new(9.0, 0.0),
+                Vector2::new(9.0, 3.0),
+                Vector2::new(1.0, 3.0),
+            ],
+        );
+
+        // Every cell of the zone nearer than x = 7 is walled off from the start
+        let zone_path = calculate_path_to_zone(&astar, &grid, Vector2::new(0, 9), &zone).unwrap();
+        assert!(zone_path.path.last().unwrap().x >= 7);
+    }
+}
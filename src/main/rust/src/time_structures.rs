@@ -1,6 +1,8 @@
-use nalgebra::Vector2;
+use nalgebra::{Vector2, Vector3};
 use splines::{Interpolation, Key, Spline};
 
+use crate::{hybrid_grid::HybridGrid, pathfinding};
+
 #[derive(Debug)]
 pub struct TimedVector2 {
     pub position: Vector2<f64>,
@@ -118,6 +120,48 @@ impl TimedPath {
         Some(Vector2::new(x, y))
     }
 
+    /// Returns the first time at which the path is inside a keep-out zone of `grid` that is active
+    /// then, or `None` if it keeps out. The path is sampled every `time_step` seconds, so the
+    /// spline has to be computed. Positions are in grid cells and times in seconds after the
+    /// grid's match time.
+    pub fn find_keep_out_violation(&self, grid: &HybridGrid, time_step: f64) -> Option<f64> {
+        let samples = (self.total_time / time_step).ceil() as usize;
+        let waypoints: Vec<Vector3<f64>> = (0..=samples)
+            .filter_map(|i| {
+                let time = (i as f64 * time_step).min(self.total_time);
+                let position = self.get_position_at(time)?;
+                Some(Vector3::new(position.x, position.y, time))
+            })
+            .collect();
+
+        pathfinding::find_keep_out_violation(grid, &waypoints).map(|i| waypoints[i].z)
+    }
+
+    /// Returns the same path with every key feature position and direction transformed, e.g. into
+    /// the other alliance's frame. The transforms must keep distances for the timing to stay right.
+    pub fn transformed(
+        &self,
+        position: impl Fn(Vector2<f64>) -> Vector2<f64>,
+        direction: impl Fn(Vector2<f64>) -> Vector2<f64>,
+    ) -> Self {
+        let mut path = Self::new(self.max_speed, self.accel);
+        path.key_feature_path = self
+            .key_feature_path
+            .iter()
+            .map(|feature| TimedVector2 {
+                position: position(feature.position),
+                direction: direction(feature.direction),
+                time_to_reach: feature.time_to_reach,
+            })
+            .collect();
+        path.total_time = self.total_time;
+        if self.spline_x.is_some() {
+            path.compute_spline();
+        }
+
+        path
+    }
+
     /// Returns the approximate time at a given position by finding the closest point on the path
     pub fn get_time_at_position(&self, pos: Vector2<f64>) -> Option<f64> {
         if self.spline_x.is_none() || self.spline_y.is_none() {